@@ -0,0 +1,382 @@
+use std::{collections::BTreeMap, fmt, future::Future, io::Write, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Report, Result, bail};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+use crate::crypto;
+
+// How long cached secrets are kept and used when `secrets_cache_ttl` isn't set
+pub(crate) const DEFAULT_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+const KEY_SALT: &[u8] = b"bws-secrets-cache";
+
+pub(crate) struct SecretsCache {
+    path: PathBuf,
+    key: [u8; 32],
+    ttl: u64,
+    max_stale: u64,
+    offline: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct CacheContents {
+    organization_id: Option<Uuid>,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    synced_at: DateTime<Utc>,
+    value: serde_json::Value,
+}
+
+// The key is left out, so that it's never printed
+impl fmt::Debug for SecretsCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretsCache")
+            .field("path", &self.path)
+            .field("ttl", &self.ttl)
+            .field("max_stale", &self.max_stale)
+            .field("offline", &self.offline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecretsCache {
+    pub(crate) fn new(
+        path: PathBuf,
+        access_token: &str,
+        ttl: u64,
+        max_stale: Option<u64>,
+        offline: bool,
+    ) -> Result<Self> {
+        // The encryption key is the part of the access token after the `:`
+        let Some((_, encryption_key)) = access_token.rsplit_once(':') else {
+            bail!("Invalid access token, the secrets cache can't be used");
+        };
+
+        // The encryption key is random, so deriving a key from it doesn't need to be slow
+        let key = crypto::derive_key(encryption_key, KEY_SALT, 1, 1024, 1)?;
+
+        Ok(SecretsCache {
+            path,
+            key,
+            ttl,
+            max_stale: max_stale.unwrap_or(ttl),
+            offline,
+        })
+    }
+
+    pub(crate) fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub(crate) fn go_offline(&mut self) {
+        self.offline = true;
+    }
+
+    pub(crate) fn organization_id(&self) -> Result<Uuid> {
+        match self.read()?.organization_id {
+            Some(organization_id) => Ok(organization_id),
+            None => bail!("There are no cached secrets"),
+        }
+    }
+
+    pub(crate) async fn cached<T>(
+        &self,
+        organization_id: Uuid,
+        request: &str,
+        fetch: impl Future<Output = Result<T>>,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if self.offline {
+            return self.load(request);
+        }
+
+        match fetch.await {
+            Ok(value) => {
+                if let Err(e) = self.store(organization_id, request, &value) {
+                    eprintln!("Warning: failed to update the secrets cache: {}", e);
+                }
+                Ok(value)
+            }
+            Err(e) if is_connectivity_error(&e) => match self.load(request) {
+                Ok(value) => {
+                    eprintln!("Warning: {}\nUsing the cached secrets instead.", e);
+                    Ok(value)
+                }
+                Err(_) => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    fn load<T: DeserializeOwned>(&self, request: &str) -> Result<T> {
+        let Some(entry) = self.read()?.entries.remove(request) else {
+            bail!("There are no cached secrets for this command");
+        };
+
+        let age = (Utc::now() - entry.synced_at).num_seconds();
+        if age > self.max_stale as i64 {
+            bail!(
+                "The cached secrets were synced {} seconds ago, more than the {} seconds allowed",
+                age,
+                self.max_stale
+            );
+        }
+
+        Ok(serde_json::from_value(entry.value)?)
+    }
+
+    fn store<T: Serialize>(&self, organization_id: Uuid, request: &str, value: &T) -> Result<()> {
+        // Start over when the cache can't be read, such as after the access token was replaced
+        let mut contents = self.read().unwrap_or_default();
+        if contents.organization_id != Some(organization_id) {
+            contents = CacheContents::default();
+        }
+
+        let now = Utc::now();
+        contents
+            .entries
+            .retain(|_, entry| (now - entry.synced_at).num_seconds() <= self.ttl as i64);
+        contents.organization_id = Some(organization_id);
+        contents.entries.insert(
+            request.to_owned(),
+            CacheEntry {
+                synced_at: now,
+                value: serde_json::to_value(value)?,
+            },
+        );
+
+        let encrypted = crypto::encrypt(&self.key, &serde_json::to_vec(&contents)?)?;
+
+        // Temporary files are only readable by their owner, and replace the cache at once
+        let directory = self.path.parent().unwrap_or(std::path::Path::new("."));
+        let mut file = tempfile::NamedTempFile::new_in(directory)?;
+        file.write_all(&encrypted)?;
+        file.persist(&self.path)?;
+
+        Ok(())
+    }
+
+    fn read(&self) -> Result<CacheContents> {
+        let encrypted = match std::fs::read(&self.path) {
+            Ok(encrypted) => encrypted,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(CacheContents::default());
+            }
+            Err(e) => bail!("Failed to read the secrets cache: {}", e),
+        };
+
+        let contents = match crypto::decrypt(&self.key, &encrypted) {
+            Ok(contents) => contents,
+            Err(_) => bail!("The secrets cache can't be decrypted with this access token"),
+        };
+        Ok(serde_json::from_slice(&contents)?)
+    }
+}
+
+// Failed connections, timeouts and server errors, rather than the server refusing the request
+pub(crate) fn is_connectivity_error(error: &Report) -> bool {
+    use std::io::ErrorKind;
+
+    // The SDK reports the status of error responses as `[<status>] <message>`
+    if let Some(status) = error.chain().find_map(|e| response_status(&e.to_string())) {
+        return status >= 500;
+    }
+
+    error
+        .chain()
+        .any(|e| match e.downcast_ref::<std::io::Error>() {
+            Some(e) => matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotConnected
+                    | ErrorKind::HostUnreachable
+                    | ErrorKind::NetworkUnreachable
+                    | ErrorKind::TimedOut
+            ),
+            None => {
+                let message = e.to_string().to_lowercase();
+                [
+                    "error sending request",
+                    "error trying to connect",
+                    "dns error",
+                    "timed out",
+                ]
+                .iter()
+                .any(|marker| message.contains(marker))
+            }
+        })
+}
+
+fn response_status(message: &str) -> Option<u16> {
+    message.match_indices('[').find_map(|(start, _)| {
+        let status = message.get(start + 1..start + 4)?;
+        let after = message.get(start + 4..)?;
+        match status.bytes().all(|b| b.is_ascii_digit())
+            && (after.starts_with(' ') || after.starts_with(']'))
+        {
+            true => status.parse().ok(),
+            false => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCESS_TOKEN: &str = "0.ec2c1d46-6a4b-4751-a310-af9601317f2d.C2IgxjjLF7qSshsbwe8JGcbM075YXw:X8vbvA0bduihIDe/qrzIQQ==";
+
+    fn cache(dir: &tempfile::TempDir, max_stale: Option<u64>, offline: bool) -> SecretsCache {
+        SecretsCache::new(
+            dir.path().join("cache"),
+            ACCESS_TOKEN,
+            DEFAULT_TTL_SECONDS,
+            max_stale,
+            offline,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn debug_output_leaves_out_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(&dir, None, false);
+
+        let debug = format!("{:?}", cache);
+        assert!(debug.starts_with("SecretsCache"));
+        assert!(!debug.contains(&format!("{:?}", cache.key)));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_cached_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let organization_id = Uuid::new_v4();
+        let cache = cache(&dir, None, false);
+
+        let fetched = cache
+            .cached(organization_id, "request", async { Ok("value".to_owned()) })
+            .await
+            .unwrap();
+        assert_eq!("value", fetched);
+
+        let cached: String = cache
+            .cached(organization_id, "request", async {
+                Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+            })
+            .await
+            .unwrap();
+        assert_eq!("value", cached);
+        assert!(
+            cache
+                .cached::<String>(organization_id, "other", async {
+                    Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+                })
+                .await
+                .is_err()
+        );
+
+        // The cache is encrypted
+        let contents = std::fs::read(dir.path().join("cache")).unwrap();
+        assert!(!String::from_utf8_lossy(&contents).contains("value"));
+        assert_eq!(organization_id, cache.organization_id().unwrap());
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_connectivity_errors_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let organization_id = Uuid::new_v4();
+        let cache = cache(&dir, None, false);
+        cache.store(organization_id, "request", &1).unwrap();
+
+        for message in [
+            "error sending request for url (https://api.bitwarden.com/): operation timed out",
+            "Received error message from server: [500 Internal Server Error] Internal error",
+            "Received error message from server: [503 Service Unavailable]",
+        ] {
+            let value: i32 = cache
+                .cached(organization_id, "request", async { bail!(message) })
+                .await
+                .unwrap();
+            assert_eq!(1, value, "{}", message);
+        }
+
+        for message in [
+            "Received error message from server: [401 Unauthorized] Invalid token",
+            "Received error message from server: [403 Forbidden]",
+            "Received error message from server: [404 Not Found] Resource not found.",
+            "Secret 'API_KEY' could not be retrieved",
+        ] {
+            let error = cache
+                .cached::<i32>(organization_id, "request", async { bail!(message) })
+                .await
+                .unwrap_err();
+            assert_eq!(message, error.to_string());
+        }
+    }
+
+    #[test]
+    fn connectivity_errors() {
+        let io_error = |kind| Report::from(std::io::Error::from(kind));
+        assert!(is_connectivity_error(&io_error(
+            std::io::ErrorKind::ConnectionRefused
+        )));
+        assert!(is_connectivity_error(&io_error(
+            std::io::ErrorKind::TimedOut
+        )));
+        assert!(is_connectivity_error(
+            &io_error(std::io::ErrorKind::ConnectionReset).wrap_err("error sending request")
+        ));
+        assert!(!is_connectivity_error(&io_error(
+            std::io::ErrorKind::PermissionDenied
+        )));
+        assert!(!is_connectivity_error(&color_eyre::eyre::eyre!(
+            "Access token is not in a valid format"
+        )));
+        assert!(!is_connectivity_error(&color_eyre::eyre::eyre!(
+            "Secret [12345] not found"
+        )));
+        assert!(!is_connectivity_error(
+            &color_eyre::eyre::eyre!("[401 Unauthorized]").wrap_err("error sending request")
+        ));
+    }
+
+    #[tokio::test]
+    async fn offline_uses_fresh_enough_results_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let organization_id = Uuid::new_v4();
+        cache(&dir, None, false)
+            .store(organization_id, "request", &1)
+            .unwrap();
+
+        let offline = cache(&dir, None, true);
+        let value: i32 = offline
+            .cached(organization_id, "request", async { panic!("offline") })
+            .await
+            .unwrap();
+        assert_eq!(1, value);
+
+        let mut contents = offline.read().unwrap();
+        contents.entries.get_mut("request").unwrap().synced_at -= chrono::Duration::hours(2);
+        let encrypted =
+            crypto::encrypt(&offline.key, &serde_json::to_vec(&contents).unwrap()).unwrap();
+        std::fs::write(dir.path().join("cache"), encrypted).unwrap();
+
+        assert!(
+            cache(&dir, Some(60 * 60), true)
+                .load::<i32>("request")
+                .is_err()
+        );
+        assert!(cache(&dir, None, true).load::<i32>("request").is_ok());
+    }
+}
@@ -1,15 +1,16 @@
 use std::path::PathBuf;
 
 use bitwarden_cli::Color;
-use clap::{ArgGroup, Parser, Subcommand, ValueEnum, builder::ValueParser};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum, builder::ValueParser};
 use clap_complete::Shell;
-use uuid::Uuid;
 
 pub(crate) const ACCESS_TOKEN_KEY_VAR_NAME: &str = "BWS_ACCESS_TOKEN";
 pub(crate) const CONFIG_FILE_KEY_VAR_NAME: &str = "BWS_CONFIG_FILE";
 pub(crate) const PROFILE_KEY_VAR_NAME: &str = "BWS_PROFILE";
 pub(crate) const SERVER_URL_KEY_VAR_NAME: &str = "BWS_SERVER_URL";
 pub(crate) const UUIDS_AS_KEYNAMES_VAR_NAME: &str = "BWS_UUIDS_AS_KEYNAMES";
+pub(crate) const MOUNT_DIR_VAR_NAME: &str = "BWS_MOUNT_DIR";
+pub(crate) const PASSPHRASE_VAR_NAME: &str = "BWS_PASSPHRASE";
 
 pub(crate) const DEFAULT_CONFIG_FILENAME: &str = "config";
 pub(crate) const DEFAULT_CONFIG_DIRECTORY: &str = ".config/bws";
@@ -22,6 +23,21 @@ pub(crate) enum ProfileKey {
     server_identity,
     state_dir,
     state_opt_out,
+    secrets_cache,
+    secrets_cache_ttl,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, ValueEnum, Debug)]
+#[value(rename_all = "snake_case")]
+pub(crate) enum SecretField {
+    Id,
+    OrganizationId,
+    ProjectId,
+    Key,
+    Value,
+    Note,
+    CreationDate,
+    RevisionDate,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -35,6 +51,42 @@ pub(crate) enum Output {
     None,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+#[expect(clippy::upper_case_acronyms)]
+pub(crate) enum ImportFormat {
+    Env,
+    JSON,
+    YAML,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+#[expect(clippy::upper_case_acronyms)]
+pub(crate) enum ExportFormat {
+    Env,
+    JSON,
+    YAML,
+    /// The Bitwarden Secrets Manager export format, including notes and projects
+    Bitwarden,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub(crate) enum OnChange {
+    /// Stop the command and start it again with the updated secrets
+    Restart,
+    /// Update the files in the mount directory and send a signal to the command
+    Signal,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub(crate) enum StdinMode {
+    /// Give the command the standard input of bws, including the terminal for interactive programs
+    Inherit,
+    /// Give the command an empty standard input
+    Null,
+    /// Read the command from the standard input, giving the command an empty one
+    Script,
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "bws", version, about = "Bitwarden Secrets CLI", long_about = None)]
 pub(crate) struct Cli {
@@ -98,6 +150,29 @@ pub(crate) enum Commands {
     Secret {
         #[command(subcommand)]
         cmd: SecretCommand,
+        #[command(flatten)]
+        key_names: KeyNameArgs,
+    },
+    #[command(long_about = "Render templates with secrets")]
+    Template {
+        #[command(subcommand)]
+        cmd: TemplateCommand,
+    },
+    #[command(
+        long_about = "Create projects, and create, update and delete secrets, to match a TOML or YAML manifest"
+    )]
+    Apply {
+        #[arg(help = "The manifest to apply")]
+        file: PathBuf,
+        #[arg(
+            long,
+            help = "Delete the secrets that are not in the manifest from the projects it declares. Other projects are left alone"
+        )]
+        prune: bool,
+        #[arg(long, help = "Confirm changes that delete secrets")]
+        yes: bool,
+        #[arg(long, help = "Show the plan without applying it")]
+        dry_run: bool,
     },
     #[command(long_about = "Run a command with secrets injected")]
     Run {
@@ -105,13 +180,50 @@ pub(crate) enum Commands {
         command: Vec<String>,
         #[arg(long, help = "The shell to use")]
         shell: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "shell",
+            help = "Run the command directly with its arguments as given, instead of through a shell. On Unix, bws is replaced by the command unless --mount-dir or --watch is used"
+        )]
+        no_shell: bool,
+        #[arg(
+            long,
+            value_enum,
+            help = "What the command gets as its standard input [default: script without a command, inherit otherwise]"
+        )]
+        stdin: Option<StdinMode>,
         #[arg(
             long,
             help = "Don't inherit environment variables from the current shell"
         )]
         no_inherit_env: bool,
-        #[arg(long, help = "The ID of the project to use")]
-        project_id: Option<Uuid>,
+        #[arg(
+            long,
+            visible_alias = "project",
+            help = "The ID or name of a project to use, can be repeated. When a key is in more than one project, the project given last takes precedence"
+        )]
+        project_id: Vec<String>,
+        #[arg(long, help = "Add a prefix to the names of the environment variables")]
+        prefix: Option<String>,
+        #[arg(
+            long,
+            help = "Remove a prefix from the secret keys, when they start with it"
+        )]
+        strip_prefix: Option<String>,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "KEY",
+            help = "Only use the secrets with these keys or key glob patterns, failing if one of them is missing"
+        )]
+        only: Vec<String>,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "KEY",
+            help = "Don't use the secrets with these keys or key glob patterns"
+        )]
+        exclude: Vec<String>,
         #[arg(
             long,
             global = true,
@@ -119,59 +231,459 @@ pub(crate) enum Commands {
             help = "Use the secret UUID (in its POSIX form) instead of the key name for the environment variable"
         )]
         uuids_as_keynames: bool,
+        #[arg(
+            long,
+            help = format!("Write secrets to files in a private temporary directory instead of environment variables. The directory path is exposed to the command as {}", MOUNT_DIR_VAR_NAME)
+        )]
+        mount_dir: bool,
+        #[arg(
+            long,
+            conflicts_with = "offline",
+            help = "Keep running and check for changes to the secrets, applying them to the command"
+        )]
+        watch: bool,
+        #[arg(
+            long,
+            requires = "watch",
+            value_name = "SECONDS",
+            default_value_t = 60,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "How often to check for changes to the secrets"
+        )]
+        watch_interval: u64,
+        #[arg(
+            long,
+            requires = "watch",
+            value_enum,
+            default_value_t = OnChange::Restart,
+            help = "What to do with the command when the secrets change"
+        )]
+        on_change: OnChange,
+        #[arg(
+            long,
+            requires = "watch",
+            value_name = "SIGNAL",
+            default_value = "SIGHUP",
+            help = "The signal to send to the command when using `--on-change signal`"
+        )]
+        watch_signal: String,
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 10,
+            help = "How long the command has to exit after being sent a termination signal or stopped for a restart, before it's killed"
+        )]
+        stop_timeout: u64,
+        #[arg(
+            long,
+            help = "Replace the secret values, and their base64 and URL-encoded forms, with *** in the output of the command"
+        )]
+        mask_output: bool,
+        #[command(flatten)]
+        key_names: KeyNameArgs,
+        #[command(flatten)]
+        cache: CacheArgs,
     },
 }
 
 #[derive(Subcommand, Debug)]
 pub(crate) enum SecretCommand {
+    #[command(allow_missing_positional = true)]
     Create {
         key: String,
-        value: String,
+        #[arg(
+            required_unless_present_any = ["generate", "value_file", "value_stdin"],
+            conflicts_with_all = ["generate", "value_file", "value_stdin"],
+            help = "The value of the secret, or - to type it in without it being shown"
+        )]
+        value: Option<String>,
 
-        #[arg(help = "The ID of the project this secret will be added to")]
-        project_id: Uuid,
+        #[arg(help = "The ID or name of the project this secret will be added to")]
+        project_id: String,
 
         #[arg(long, help = "An optional note to add to the secret")]
         note: Option<String>,
+
+        #[arg(long, help = "Generate a random value instead of providing one")]
+        generate: bool,
+
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["generate", "value_stdin"],
+            help = "Read the value from a file, as is"
+        )]
+        value_file: Option<PathBuf>,
+
+        #[arg(
+            long,
+            conflicts_with = "generate",
+            help = "Read the value from stdin, as is"
+        )]
+        value_stdin: bool,
+
+        #[command(flatten)]
+        generator: GeneratorArgs,
     },
     Delete {
-        secret_ids: Vec<Uuid>,
+        #[arg(help = "The IDs or keys of the secrets to delete")]
+        secrets: Vec<String>,
+
+        #[arg(
+            long,
+            visible_alias = "project",
+            help = "The ID or name of the project to look up the secret keys in"
+        )]
+        project_id: Option<String>,
     },
+    #[command(long_about = "Copy secrets to another project, keeping the original secrets")]
+    Copy(SecretTransferArgs),
+    #[command(long_about = "Move secrets to another project")]
+    Move(SecretTransferArgs),
     #[clap(group = ArgGroup::new("edit_field").required(true).multiple(true))]
+    #[command(
+        long_about = "Edit a secret. When the secret was changed by someone else since it was read, such as while \
+            it's open in --editor or after the --if-revision timestamp, the edit is refused and bws exits \
+            with code 3. The server has no conditional update, so this check is best effort"
+    )]
     Edit {
-        secret_id: Uuid,
+        #[arg(help = "The ID or key of the secret to edit")]
+        secret: String,
         #[arg(long, group = "edit_field")]
         key: Option<String>,
-        #[arg(long, group = "edit_field")]
+        #[arg(
+            long,
+            group = "edit_field",
+            help = "The new value, or - to type it in without it being shown"
+        )]
         value: Option<String>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            group = "edit_field",
+            conflicts_with_all = ["value", "value_stdin"],
+            help = "Read the new value from a file, as is"
+        )]
+        value_file: Option<PathBuf>,
+        #[arg(
+            long,
+            group = "edit_field",
+            conflicts_with = "value",
+            help = "Read the new value from stdin, as is"
+        )]
+        value_stdin: bool,
+        #[arg(
+            long,
+            group = "edit_field",
+            conflicts_with_all = ["value", "value_file", "value_stdin"],
+            help = "Replace the value with a new random one"
+        )]
+        regenerate: bool,
+        #[command(flatten)]
+        generator: GeneratorArgs,
         #[arg(long, group = "edit_field")]
         note: Option<String>,
-        #[arg(long, group = "edit_field")]
-        project_id: Option<Uuid>,
+        #[arg(
+            long,
+            visible_alias = "project",
+            group = "edit_field",
+            help = "The ID or name of the project to move the secret to"
+        )]
+        project_id: Option<String>,
+        #[arg(
+            long,
+            group = "edit_field",
+            conflicts_with_all = ["key", "value", "value_file", "value_stdin", "regenerate", "note"],
+            help = "Edit the key, value and note in $EDITOR"
+        )]
+        editor: bool,
+        #[arg(
+            long,
+            value_name = "TIMESTAMP",
+            help = "Only edit the secret if its revision date is still this RFC 3339 timestamp. Conflicts exit with code 3"
+        )]
+        if_revision: Option<String>,
+        #[arg(
+            long,
+            visible_alias = "lookup-project",
+            help = "The ID or name of the project to look up the secret key in"
+        )]
+        lookup_project_id: Option<String>,
+    },
+    #[command(long_about = "Export secrets to an env, JSON, YAML or Bitwarden export file")]
+    Export {
+        #[arg(
+            default_value = "-",
+            help = "The file to write the secrets to, or - to write them to stdout"
+        )]
+        file: PathBuf,
+
+        #[arg(
+            long,
+            visible_alias = "project",
+            help = "The ID or name of the project to export, all secrets by default"
+        )]
+        project_id: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = ExportFormat::Env, help = "The format of the export")]
+        format: ExportFormat,
+
+        #[arg(
+            long,
+            help = format!("Encrypt the export with a passphrase, read from {} or prompted for", PASSPHRASE_VAR_NAME)
+        )]
+        encrypt: bool,
     },
     Get {
-        secret_id: Uuid,
+        #[arg(help = "The ID or key of the secret")]
+        secret: String,
+
+        #[arg(
+            long,
+            visible_alias = "project",
+            help = "The ID or name of the project to look up the secret key in"
+        )]
+        project_id: Option<String>,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+    #[command(
+        long_about = "Create or update the secrets of a project from an env, JSON or YAML file"
+    )]
+    Import {
+        file: PathBuf,
+
+        #[arg(
+            long,
+            visible_alias = "project",
+            help = "The ID or name of the project to import the secrets into"
+        )]
+        project_id: String,
+
+        #[arg(
+            long,
+            value_enum,
+            help = "The format of the file, detected from its extension by default"
+        )]
+        format: Option<ImportFormat>,
+
+        #[arg(long, help = "Show the changes without applying them")]
+        dry_run: bool,
     },
     List {
-        project_id: Option<Uuid>,
+        #[arg(help = "The ID or name of the project to list the secrets of")]
+        project_id: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "PATTERN",
+            help = "Only list secrets with a key matching a glob pattern, like DB_*"
+        )]
+        key_glob: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "REGEX",
+            help = "Only list secrets with a key matching a regular expression"
+        )]
+        key_regex: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            help = "Only list secrets created after a date, like 2024-01-31 or 2024-01-31T12:00:00Z"
+        )]
+        created_after: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "DATE",
+            help = "Only list secrets changed after a date, like 2024-01-31 or 2024-01-31T12:00:00Z"
+        )]
+        revised_after: Option<String>,
+
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            help = "Only output these fields, in this order"
+        )]
+        fields: Vec<SecretField>,
+
+        #[arg(
+            long,
+            help = "Don't retrieve or output the values of the secrets. Without --project-id, finding the project of each secret takes one request per project, which --fields without project_id avoids"
+        )]
+        no_values: bool,
     },
 }
 
+// Options for generating secret values, used by `secret create --generate` and
+// `secret edit --regenerate`. Not a doc comment, as clap would use it as the command description
+#[derive(Args, Debug)]
+pub(crate) struct GeneratorArgs {
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(4..=128),
+        help = "The length of the generated value [default: 32]"
+    )]
+    pub(crate) length: Option<u8>,
+    #[arg(long, help = "Don't use lowercase letters in the generated value")]
+    pub(crate) no_lowercase: bool,
+    #[arg(long, help = "Don't use uppercase letters in the generated value")]
+    pub(crate) no_uppercase: bool,
+    #[arg(long, help = "Don't use numbers in the generated value")]
+    pub(crate) no_numbers: bool,
+    #[arg(long, help = "Don't use symbols in the generated value")]
+    pub(crate) no_symbols: bool,
+    #[arg(
+        long,
+        help = "Avoid characters that are easily confused, like l and 1, in the generated value"
+    )]
+    pub(crate) avoid_ambiguous: bool,
+}
+
+// How secret keys are turned into variable names, used by `run` and the env output of the secret
+// commands
+#[derive(Args, Debug)]
+pub(crate) struct KeyNameArgs {
+    #[arg(
+        long,
+        global = true,
+        help = "Uppercase secret keys used as variable names, in `run` and the env output"
+    )]
+    pub(crate) uppercase_keys: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Replace the characters of secret keys that aren't valid in variable names with _, in `run` and the env output"
+    )]
+    pub(crate) sanitize_keys: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "A JSON or YAML file mapping secret keys to variable names, in `run` and the env output"
+    )]
+    pub(crate) key_map: Option<PathBuf>,
+}
+
+// Options for the secrets cache, used by `run` and `secret get`. The cache itself is enabled with
+// the `secrets_cache` profile key
+#[derive(Args, Debug)]
+pub(crate) struct CacheArgs {
+    #[arg(
+        long,
+        help = "Use the cached secrets without contacting the server. Requires the secrets cache to be enabled with `bws config secrets-cache true`"
+    )]
+    pub(crate) offline: bool,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "How old the cached secrets can be when they're used [default: the secrets_cache_ttl profile key, or 7 days]"
+    )]
+    pub(crate) max_stale: Option<u64>,
+}
+
+// The secrets to copy or move, and where to
+#[derive(Args, Debug)]
+pub(crate) struct SecretTransferArgs {
+    #[arg(
+        required_unless_present = "key_glob",
+        help = "The IDs or keys of the secrets"
+    )]
+    pub(crate) secrets: Vec<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "secrets",
+        help = "Select the secrets with keys matching a glob pattern instead"
+    )]
+    pub(crate) key_glob: Option<String>,
+
+    #[arg(
+        long,
+        visible_alias = "project",
+        help = "The ID or name of the project to look up the secret keys in"
+    )]
+    pub(crate) project_id: Option<String>,
+
+    #[arg(
+        long,
+        visible_alias = "to-project",
+        help = "The ID or name of the project to copy or move the secrets to"
+    )]
+    pub(crate) to_project_id: String,
+}
+
 #[derive(Subcommand, Debug)]
 pub(crate) enum ProjectCommand {
+    #[command(
+        long_about = "Create a new project with copies of the secrets of an existing project"
+    )]
+    Clone {
+        #[arg(help = "The ID or name of the project to clone")]
+        project_id: String,
+        #[arg(help = "The name of the new project")]
+        name: String,
+        #[arg(
+            long,
+            help = "Create the secrets with empty values, to be filled in later"
+        )]
+        blank_values: bool,
+        #[arg(
+            long,
+            value_name = "OLD=NEW",
+            help = "Replace the OLD prefix of secret keys with NEW, can be repeated"
+        )]
+        rename_prefix: Vec<String>,
+    },
     Create {
         name: String,
     },
     Delete {
-        project_ids: Vec<Uuid>,
+        #[arg(help = "The IDs or names of the projects to delete")]
+        project_ids: Vec<String>,
+    },
+    #[command(long_about = "Compare the secrets of two projects by key")]
+    Diff {
+        #[arg(help = "The ID or name of the project to compare from")]
+        from_project_id: String,
+        #[arg(help = "The ID or name of the project to compare to")]
+        to_project_id: String,
+        #[arg(long, help = "Show the values of the secrets instead of masking them")]
+        show_values: bool,
     },
     Edit {
-        project_id: Uuid,
+        #[arg(help = "The ID or name of the project to edit")]
+        project_id: String,
         #[arg(long, group = "edit_field")]
         name: String,
     },
     Get {
-        project_id: Uuid,
+        #[arg(help = "The ID or name of the project")]
+        project_id: String,
     },
     List,
 }
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum TemplateCommand {
+    #[command(
+        long_about = "Render a template, replacing every {{ bws \"KEY\" }} and {{ bws_id \"ID\" }} placeholder with the value of the secret"
+    )]
+    Render {
+        #[arg(help = "The template to render, or - to read it from stdin")]
+        template: PathBuf,
+        #[arg(help = "The file to write the rendered template to, or - to write it to stdout")]
+        destination: PathBuf,
+        #[arg(
+            long,
+            visible_alias = "project",
+            help = "The ID or name of the project to look up the secret keys in"
+        )]
+        project_id: Option<String>,
+    },
+}
@@ -0,0 +1,669 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient,
+        projects::{ProjectCreateRequest, ProjectResponse, ProjectsListRequest},
+        secrets::{SecretCreateRequest, SecretPutRequest, SecretResponse, SecretsDeleteRequest},
+    },
+};
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::command::secret::get_secrets;
+
+#[derive(Debug)]
+pub(crate) struct ApplyCommandModel {
+    pub(crate) file: PathBuf,
+    pub(crate) prune: bool,
+    pub(crate) yes: bool,
+    pub(crate) dry_run: bool,
+}
+
+/// The projects and secrets that should exist, as declared in a manifest file.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    projects: Vec<ManifestProject>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ManifestProject {
+    name: String,
+    #[serde(default)]
+    secrets: Vec<ManifestSecret>,
+}
+
+/// A secret whose value is given by exactly one of `value`, `env` or `file`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ManifestSecret {
+    key: String,
+    value: Option<String>,
+    /// The environment variable holding the value
+    env: Option<String>,
+    /// The file holding the value, relative to the manifest
+    file: Option<PathBuf>,
+    /// The note of the secret. When missing, the note of an existing secret is left as is
+    note: Option<String>,
+}
+
+/// A secret from the manifest, with its value resolved.
+#[derive(Clone, Debug, PartialEq)]
+struct DesiredSecret {
+    key: String,
+    value: String,
+    note: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    CreateProject {
+        name: String,
+    },
+    CreateSecret {
+        project: String,
+        secret: DesiredSecret,
+    },
+    UpdateSecret {
+        project: String,
+        id: Uuid,
+        key: String,
+        value: String,
+        note: String,
+        value_changed: bool,
+        note_changed: bool,
+    },
+    DeleteSecret {
+        project: String,
+        id: Uuid,
+        key: String,
+    },
+}
+
+impl Change {
+    fn is_destructive(&self) -> bool {
+        matches!(self, Change::DeleteSecret { .. })
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::CreateProject { name } => write!(f, "+ project {}", name),
+            Change::CreateSecret { project, secret } => {
+                write!(f, "+ secret {}/{}", project, secret.key)
+            }
+            Change::UpdateSecret {
+                project,
+                key,
+                value_changed,
+                note_changed,
+                ..
+            } => {
+                let fields = [(*value_changed, "value"), (*note_changed, "note")]
+                    .into_iter()
+                    .filter_map(|(changed, field)| changed.then_some(field))
+                    .join(", ");
+                write!(f, "~ secret {}/{} ({})", project, key, fields)
+            }
+            Change::DeleteSecret { project, key, .. } => {
+                write!(f, "- secret {}/{}", project, key)
+            }
+        }
+    }
+}
+
+pub(crate) async fn apply(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    apply: ApplyCommandModel,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+
+    let manifest = read_manifest(&apply.file)?;
+    let base_dir = apply.file.parent().unwrap_or(Path::new(""));
+    let desired = resolve(manifest, base_dir)?;
+
+    let projects = client
+        .projects()
+        .list(&ProjectsListRequest { organization_id })
+        .await?
+        .data;
+
+    let mut existing_secrets = HashMap::new();
+    for project in &projects {
+        if desired.iter().any(|(name, _)| name == &project.name) {
+            let secrets = get_secrets(&client, organization_id, Some(project.id)).await?;
+            existing_secrets.insert(project.id, secrets);
+        }
+    }
+
+    let changes = plan(&desired, &projects, &existing_secrets, apply.prune)?;
+    if changes.is_empty() {
+        println!("No changes. The projects and secrets match the manifest.");
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("{}", change);
+    }
+    let count = |predicate: fn(&Change) -> bool| changes.iter().filter(|c| predicate(c)).count();
+    let deletions = count(Change::is_destructive);
+    println!(
+        "\nPlan: {} to create, {} to update, {} to delete.",
+        count(|c| matches!(
+            c,
+            Change::CreateProject { .. } | Change::CreateSecret { .. }
+        )),
+        count(|c| matches!(c, Change::UpdateSecret { .. })),
+        deletions,
+    );
+
+    if apply.dry_run {
+        eprintln!("Dry run, no changes were made.");
+        return Ok(());
+    }
+
+    if deletions > 0 && !apply.yes {
+        bail!(
+            "The plan deletes {} secrets. Run again with --yes to apply it",
+            deletions
+        );
+    }
+
+    execute(&client, organization_id, &projects, changes).await
+}
+
+async fn execute(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    projects: &[ProjectResponse],
+    changes: Vec<Change>,
+) -> Result<()> {
+    let mut project_ids: HashMap<String, Uuid> =
+        projects.iter().map(|p| (p.name.clone(), p.id)).collect();
+    let mut secrets_to_delete: HashMap<Uuid, String> = HashMap::new();
+    let mut failed: Vec<(String, String)> = Vec::new();
+
+    // Projects are created first, as the secrets added to them need their IDs
+    for change in &changes {
+        if let Change::CreateProject { name } = change {
+            let result = client
+                .projects()
+                .create(&ProjectCreateRequest {
+                    organization_id,
+                    name: name.clone(),
+                })
+                .await;
+            match result {
+                Ok(project) => {
+                    project_ids.insert(name.clone(), project.id);
+                }
+                Err(e) => failed.push((format!("project {}", name), e.to_string())),
+            }
+        }
+    }
+
+    for change in changes {
+        let (description, result) = match change {
+            Change::CreateSecret { project, secret } => {
+                // The secrets of a project that couldn't be created are skipped, the project
+                // failure is reported instead
+                let Some(project_id) = project_ids.get(&project) else {
+                    continue;
+                };
+                let description = format!("{}/{}", project, secret.key);
+                let result = client
+                    .secrets()
+                    .create(&SecretCreateRequest {
+                        organization_id,
+                        key: secret.key,
+                        value: secret.value,
+                        note: secret.note.unwrap_or_default(),
+                        project_ids: Some(vec![*project_id]),
+                    })
+                    .await;
+                (description, result.map(|_| ()))
+            }
+            Change::UpdateSecret {
+                project,
+                id,
+                key,
+                value,
+                note,
+                value_changed,
+                ..
+            } => {
+                let description = format!("{}/{}", project, key);
+                let result = client
+                    .secrets()
+                    .update(&SecretPutRequest {
+                        id,
+                        organization_id,
+                        key,
+                        value,
+                        note,
+                        project_ids: Some(vec![project_ids[&project]]),
+                        value_changed,
+                    })
+                    .await;
+                (description, result.map(|_| ()))
+            }
+            Change::DeleteSecret { project, id, key } => {
+                secrets_to_delete.insert(id, format!("{}/{}", project, key));
+                continue;
+            }
+            Change::CreateProject { .. } => continue,
+        };
+
+        if let Err(e) = result {
+            failed.push((description, e.to_string()));
+        }
+    }
+
+    if !secrets_to_delete.is_empty() {
+        let result = client
+            .secrets()
+            .delete(SecretsDeleteRequest {
+                ids: secrets_to_delete.keys().copied().collect(),
+            })
+            .await;
+        match result {
+            Ok(result) => failed.extend(result.data.into_iter().filter_map(|r| {
+                let description = secrets_to_delete
+                    .remove(&r.id)
+                    .unwrap_or_else(|| r.id.to_string());
+                r.error.map(|e| (description, e))
+            })),
+            Err(e) => failed.extend(
+                secrets_to_delete
+                    .into_values()
+                    .map(|description| (description, e.to_string())),
+            ),
+        }
+    }
+
+    match failed.len() {
+        2.. => eprintln!("{} changes had errors:", failed.len()),
+        1 => eprintln!("{} change had an error:", failed.len()),
+        _ => println!("Apply complete."),
+    }
+
+    for failure in &failed {
+        eprintln!("{}: {}", failure.0, failure.1);
+    }
+
+    if !failed.is_empty() {
+        bail!("Errors when attempting to apply the manifest.");
+    }
+
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest> {
+    let content = std::fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let manifest = match extension.as_str() {
+        "toml" => toml::from_str(&content).map_err(|e| e.to_string()),
+        "yaml" | "yml" => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+        _ => bail!(
+            "Unsupported manifest {}, expected a .toml, .yaml or .yml file",
+            path.display()
+        ),
+    };
+
+    match manifest {
+        Ok(manifest) => Ok(manifest),
+        Err(e) => bail!("{}: {}", path.display(), e),
+    }
+}
+
+/// Validates the manifest and reads the value of every secret from its source.
+fn resolve(manifest: Manifest, base_dir: &Path) -> Result<Vec<(String, Vec<DesiredSecret>)>> {
+    if let Some(duplicate) = manifest
+        .projects
+        .iter()
+        .map(|p| &p.name)
+        .duplicates()
+        .next()
+    {
+        bail!("Project '{}' is declared more than once", duplicate);
+    }
+
+    manifest
+        .projects
+        .into_iter()
+        .map(|project| {
+            if let Some(duplicate) = project.secrets.iter().map(|s| &s.key).duplicates().next() {
+                bail!(
+                    "Secret '{}' is declared more than once in project '{}'",
+                    duplicate,
+                    project.name
+                );
+            }
+
+            let secrets = project
+                .secrets
+                .into_iter()
+                .map(|secret| {
+                    Ok(DesiredSecret {
+                        value: read_value(&secret, &project.name, base_dir)?,
+                        key: secret.key,
+                        note: secret.note,
+                    })
+                })
+                .collect::<Result<_>>()?;
+
+            Ok((project.name, secrets))
+        })
+        .collect()
+}
+
+fn read_value(secret: &ManifestSecret, project: &str, base_dir: &Path) -> Result<String> {
+    match (&secret.value, &secret.env, &secret.file) {
+        (Some(value), None, None) => Ok(value.clone()),
+        (None, Some(var), None) => match std::env::var(var) {
+            Ok(value) => Ok(value),
+            Err(_) => bail!(
+                "Secret '{}' in project '{}': environment variable '{}' is not set",
+                secret.key,
+                project,
+                var
+            ),
+        },
+        (None, None, Some(file)) => match std::fs::read_to_string(base_dir.join(file)) {
+            Ok(value) => Ok(value),
+            Err(e) => bail!(
+                "Secret '{}' in project '{}': failed to read {}: {}",
+                secret.key,
+                project,
+                file.display(),
+                e
+            ),
+        },
+        _ => bail!(
+            "Secret '{}' in project '{}' must have exactly one of `value`, `env` or `file`",
+            secret.key,
+            project
+        ),
+    }
+}
+
+/// Works out the changes that make the server match the manifest.
+fn plan(
+    desired: &[(String, Vec<DesiredSecret>)],
+    projects: &[ProjectResponse],
+    existing_secrets: &HashMap<Uuid, Vec<SecretResponse>>,
+    prune: bool,
+) -> Result<Vec<Change>> {
+    // Only the projects the manifest declares, which are also the only ones pruned, need unique
+    // names
+    if let Some(duplicate) = projects
+        .iter()
+        .map(|p| &p.name)
+        .filter(|name| desired.iter().any(|(declared, _)| declared == *name))
+        .duplicates()
+        .next()
+    {
+        bail!(
+            "Multiple projects with name: '{}'. Use unique names for projects",
+            duplicate
+        );
+    }
+
+    let mut changes = Vec::new();
+
+    for (name, secrets) in desired {
+        let Some(project) = projects.iter().find(|p| &p.name == name) else {
+            changes.push(Change::CreateProject { name: name.clone() });
+            changes.extend(secrets.iter().map(|secret| Change::CreateSecret {
+                project: name.clone(),
+                secret: secret.clone(),
+            }));
+            continue;
+        };
+
+        let existing = existing_secrets
+            .get(&project.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if let Some(duplicate) = existing.iter().map(|s| &s.key).duplicates().next() {
+            bail!(
+                "Multiple secrets with name: '{}' in project '{}'. Use unique names for secrets",
+                duplicate,
+                name
+            );
+        }
+
+        for secret in secrets {
+            let Some(current) = existing.iter().find(|s| s.key == secret.key) else {
+                changes.push(Change::CreateSecret {
+                    project: name.clone(),
+                    secret: secret.clone(),
+                });
+                continue;
+            };
+
+            let value_changed = current.value != secret.value;
+            let note_changed = secret.note.as_ref().is_some_and(|n| n != &current.note);
+            if value_changed || note_changed {
+                changes.push(Change::UpdateSecret {
+                    project: name.clone(),
+                    id: current.id,
+                    key: secret.key.clone(),
+                    value: secret.value.clone(),
+                    note: secret.note.clone().unwrap_or_else(|| current.note.clone()),
+                    value_changed,
+                    note_changed,
+                });
+            }
+        }
+
+        if prune {
+            changes.extend(
+                existing
+                    .iter()
+                    .filter(|s| !secrets.iter().any(|secret| secret.key == s.key))
+                    .map(|s| Change::DeleteSecret {
+                        project: name.clone(),
+                        id: s.id,
+                        key: s.key.clone(),
+                    }),
+            );
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{project, project_secret};
+
+    fn desired(key: &str, value: &str) -> DesiredSecret {
+        DesiredSecret {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn manifest_formats() {
+        let toml: Manifest = toml::from_str(
+            r#"
+[[projects]]
+name = "prod"
+
+[[projects.secrets]]
+key = "LEVEL"
+value = "debug"
+"#,
+        )
+        .unwrap();
+        let yaml: Manifest = serde_yaml::from_str(
+            "projects:\n  - name: prod\n    secrets:\n      - key: LEVEL\n        value: debug\n",
+        )
+        .unwrap();
+
+        for manifest in [toml, yaml] {
+            assert_eq!(
+                vec![("prod".to_owned(), vec![desired("LEVEL", "debug")])],
+                resolve(manifest, Path::new("")).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_requires_a_single_source() {
+        let manifest: Manifest =
+            toml::from_str("[[projects]]\nname = \"prod\"\n[[projects.secrets]]\nkey = \"A\"\n")
+                .unwrap();
+        assert!(resolve(manifest, Path::new("")).is_err());
+
+        let manifest: Manifest = toml::from_str(
+            "[[projects]]\nname = \"prod\"\n[[projects.secrets]]\nkey = \"A\"\nvalue = \"a\"\nenv = \"A\"\n",
+        )
+        .unwrap();
+        assert!(resolve(manifest, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn plan_creates_updates_and_prunes() {
+        let prod = project("prod");
+        let legacy = project("legacy");
+        let existing = HashMap::from([(
+            prod.id,
+            vec![
+                project_secret(prod.id, "SAME", "1"),
+                project_secret(prod.id, "CHANGED", "old"),
+                project_secret(prod.id, "REMOVED", "x"),
+            ],
+        )]);
+        let desired = || {
+            vec![
+                (
+                    "prod".to_owned(),
+                    vec![
+                        desired("SAME", "1"),
+                        desired("CHANGED", "new"),
+                        desired("ADDED", "2"),
+                    ],
+                ),
+                ("staging".to_owned(), vec![desired("ADDED", "3")]),
+            ]
+        };
+        let projects = [prod, legacy];
+
+        let changes: Vec<String> = plan(&desired(), &projects, &existing, false)
+            .unwrap()
+            .iter()
+            .map(Change::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "~ secret prod/CHANGED (value)",
+                "+ secret prod/ADDED",
+                "+ project staging",
+                "+ secret staging/ADDED",
+            ],
+            changes
+        );
+
+        let changes: Vec<String> = plan(&desired(), &projects, &existing, true)
+            .unwrap()
+            .iter()
+            .map(Change::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "~ secret prod/CHANGED (value)",
+                "+ secret prod/ADDED",
+                "- secret prod/REMOVED",
+                "+ project staging",
+                "+ secret staging/ADDED",
+            ],
+            changes
+        );
+    }
+
+    #[test]
+    fn plan_only_requires_unique_names_for_declared_projects() {
+        let desired = vec![("prod".to_owned(), vec![desired("KEY", "value")])];
+
+        let projects = [project("prod"), project("other"), project("other")];
+        let existing = HashMap::from([(
+            projects[0].id,
+            vec![project_secret(projects[0].id, "KEY", "value")],
+        )]);
+        assert!(
+            plan(&desired, &projects, &existing, true)
+                .unwrap()
+                .is_empty()
+        );
+
+        let projects = [project("prod"), project("prod")];
+        assert!(plan(&desired, &projects, &HashMap::new(), true).is_err());
+    }
+
+    #[test]
+    fn prune_leaves_projects_missing_from_the_manifest() {
+        let prod = project("prod");
+        let unmanaged = project("unmanaged");
+        let existing = HashMap::from([
+            (prod.id, vec![project_secret(prod.id, "KEY", "value")]),
+            (
+                unmanaged.id,
+                vec![project_secret(unmanaged.id, "OTHER", "value")],
+            ),
+        ]);
+        let desired = vec![("prod".to_owned(), vec![desired("KEY", "value")])];
+
+        assert!(
+            plan(&desired, &[prod, unmanaged], &existing, true)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn plan_keeps_existing_notes_unless_declared() {
+        let prod = project("prod");
+        let mut current = project_secret(prod.id, "KEY", "value");
+        current.note = "existing".to_owned();
+        let existing = HashMap::from([(prod.id, vec![current])]);
+
+        let unchanged = vec![("prod".to_owned(), vec![desired("KEY", "value")])];
+        assert!(
+            plan(&unchanged, std::slice::from_ref(&prod), &existing, false)
+                .unwrap()
+                .is_empty()
+        );
+
+        let mut with_note = desired("KEY", "value");
+        with_note.note = Some("new".to_owned());
+        let changes = plan(
+            &[("prod".to_owned(), vec![with_note])],
+            std::slice::from_ref(&prod),
+            &existing,
+            false,
+        )
+        .unwrap();
+        assert_eq!("~ secret prod/KEY (note)", changes[0].to_string());
+    }
+}
@@ -1,6 +1,8 @@
+pub(crate) mod apply;
 pub(crate) mod project;
 pub(crate) mod run;
 pub(crate) mod secret;
+pub(crate) mod template;
 
 use std::{path::PathBuf, str::FromStr};
 
@@ -56,6 +58,20 @@ pub(crate) fn config(
                     (ProfileKey::state_opt_out, value)
                 }
             }
+            (Some(ProfileKey::secrets_cache), Some(value)) => {
+                if util::string_to_bool(value.as_str()).is_err() {
+                    bail!("Profile key \"secrets_cache\" must be \"true\" or \"false\"");
+                } else {
+                    (ProfileKey::secrets_cache, value)
+                }
+            }
+            (Some(ProfileKey::secrets_cache_ttl), Some(value)) => {
+                if value.trim().parse::<u64>().is_err() {
+                    bail!("Profile key \"secrets_cache_ttl\" must be a number of seconds");
+                } else {
+                    (ProfileKey::secrets_cache_ttl, value)
+                }
+            }
             (Some(name), Some(value)) => (name, value),
         };
 
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient, projects::ProjectCreateRequest, secrets::SecretCreateRequest,
+    },
+};
+use color_eyre::eyre::{Result, bail};
+use uuid::Uuid;
+
+use super::resolve_project_id;
+use crate::{
+    command::secret::get_secrets,
+    render::{OutputSettings, serialize_response},
+};
+
+#[derive(Debug)]
+pub(crate) struct ProjectCloneCommandModel {
+    pub(crate) project_id: String,
+    pub(crate) name: String,
+    pub(crate) blank_values: bool,
+    pub(crate) rename_prefixes: Vec<String>,
+}
+
+pub(crate) async fn clone(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    clone: ProjectCloneCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let renames = parse_renames(&clone.rename_prefixes)?;
+    let project_id = resolve_project_id(&client, organization_id, &clone.project_id).await?;
+
+    // Read and rename everything before creating the project, so that nothing is left behind when
+    // the keys can't be renamed
+    let mut secrets = get_secrets(&client, organization_id, Some(project_id)).await?;
+    secrets.sort_by(|a, b| a.key.cmp(&b.key));
+    let keys = renamed_keys(secrets.iter().map(|s| s.key.as_str()), &renames)?;
+
+    let project = client
+        .projects()
+        .create(&ProjectCreateRequest {
+            organization_id,
+            name: clone.name,
+        })
+        .await?;
+
+    let mut cloned_secrets = 0;
+    let mut secrets_failed: Vec<(Uuid, String)> = Vec::new();
+    for (secret, key) in secrets.into_iter().zip(keys) {
+        let value = match clone.blank_values {
+            true => String::new(),
+            false => secret.value,
+        };
+
+        let result = client
+            .secrets()
+            .create(&SecretCreateRequest {
+                organization_id,
+                key,
+                value,
+                note: secret.note,
+                project_ids: Some(vec![project.id]),
+            })
+            .await;
+
+        match result {
+            Ok(_) => cloned_secrets += 1,
+            Err(e) => secrets_failed.push((secret.id, e.to_string())),
+        }
+    }
+
+    match cloned_secrets {
+        1 => println!("1 secret cloned successfully."),
+        count => println!("{} secrets cloned successfully.", count),
+    }
+
+    match secrets_failed.len() {
+        2.. => eprintln!("{} secrets had errors:", secrets_failed.len()),
+        1 => eprintln!("{} secret had an error:", secrets_failed.len()),
+        _ => (),
+    }
+
+    for secret in &secrets_failed {
+        eprintln!("{}: {}", secret.0, secret.1);
+    }
+
+    if !secrets_failed.is_empty() {
+        bail!(
+            "Errors when attempting to clone secrets into project {}.",
+            project.id
+        );
+    }
+
+    serialize_response(project, output_settings);
+
+    Ok(())
+}
+
+/// Parses `OLD=NEW` prefix renames. An empty `OLD` prefix adds `NEW` to every other key.
+fn parse_renames(renames: &[String]) -> Result<Vec<(&str, &str)>> {
+    let mut parsed = Vec::new();
+    for rename in renames {
+        match rename.split_once('=') {
+            Some((old, _)) if parsed.iter().any(|(o, _)| *o == old) => {
+                bail!("The prefix '{}' is renamed more than once", old)
+            }
+            Some(pair) => parsed.push(pair),
+            None => bail!("Invalid --rename-prefix '{}', expected OLD=NEW", rename),
+        }
+    }
+
+    // The longest matching prefix wins, regardless of the order they were given in
+    parsed.sort_by_key(|(old, _)| std::cmp::Reverse(old.len()));
+    Ok(parsed)
+}
+
+fn rename_key(key: &str, renames: &[(&str, &str)]) -> String {
+    for (old, new) in renames {
+        if let Some(rest) = key.strip_prefix(old) {
+            return format!("{}{}", new, rest);
+        }
+    }
+
+    key.to_owned()
+}
+
+/// Renames the keys, failing when two different keys would end up with the same name.
+fn renamed_keys<'a>(
+    keys: impl Iterator<Item = &'a str>,
+    renames: &[(&str, &str)],
+) -> Result<Vec<String>> {
+    let mut originals: HashMap<String, &str> = HashMap::new();
+    let mut renamed = Vec::new();
+
+    for key in keys {
+        let new_key = rename_key(key, renames);
+        match originals.get(&new_key) {
+            Some(original) if *original != key => bail!(
+                "Renaming '{}' and '{}' would both result in '{}'",
+                original,
+                key,
+                new_key
+            ),
+            _ => originals.insert(new_key.clone(), key),
+        };
+        renamed.push(new_key);
+    }
+
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_longest_prefix() {
+        let input = vec![
+            "PROD_=STAGING_".to_owned(),
+            "PROD_DB_=STAGING2_DB_".to_owned(),
+            "=NEW_".to_owned(),
+        ];
+        let renames = parse_renames(&input).unwrap();
+
+        assert_eq!("STAGING2_DB_HOST", rename_key("PROD_DB_HOST", &renames));
+        assert_eq!("STAGING_API_KEY", rename_key("PROD_API_KEY", &renames));
+        assert_eq!("NEW_OTHER", rename_key("OTHER", &renames));
+        assert_eq!("OTHER", rename_key("OTHER", &[]));
+    }
+
+    #[test]
+    fn invalid_renames() {
+        assert!(parse_renames(&["PROD_".to_owned()]).is_err());
+        assert!(parse_renames(&["A=B".to_owned(), "A=C".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn renamed_keys_detect_collisions() {
+        let renames = [("PROD_", "")];
+
+        assert_eq!(
+            vec!["HOST", "HOST"],
+            renamed_keys(["PROD_HOST", "PROD_HOST"].into_iter(), &renames).unwrap()
+        );
+        assert_eq!(
+            "Renaming 'HOST' and 'PROD_HOST' would both result in 'HOST'",
+            renamed_keys(["HOST", "PROD_HOST"].into_iter(), &renames)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{SecretsManagerClient, projects::ProjectGetRequest, secrets::SecretResponse},
+};
+use bitwarden_cli::Color;
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::resolve_project_id;
+use crate::{
+    Output,
+    command::secret::get_secrets,
+    render::{OutputSettings, TableSerialize, serialize_response},
+};
+
+const MASKED_VALUE: &str = "********";
+
+#[derive(Debug)]
+pub(crate) struct ProjectDiffCommandModel {
+    pub(crate) from_project_id: String,
+    pub(crate) to_project_id: String,
+    pub(crate) show_values: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Change {
+    /// Only in the second project
+    Added,
+    /// Only in the first project
+    Removed,
+    /// In both projects, with different values
+    Changed,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SecretDiff {
+    key: String,
+    change: Change,
+    from_value: Option<String>,
+    to_value: Option<String>,
+}
+
+pub(crate) async fn diff(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    diff: ProjectDiffCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    if output_settings.output == Output::Env {
+        bail!("The env output is not supported by project diff");
+    }
+
+    let organization_id: Uuid = organization_id.into();
+    let from_project_id =
+        resolve_project_id(&client, organization_id, &diff.from_project_id).await?;
+    let to_project_id = resolve_project_id(&client, organization_id, &diff.to_project_id).await?;
+
+    let from = get_secrets(&client, organization_id, Some(from_project_id)).await?;
+    let to = get_secrets(&client, organization_id, Some(to_project_id)).await?;
+
+    let mut differences = compare(&from, &to)?;
+    if !diff.show_values {
+        for difference in &mut differences {
+            mask(&mut difference.from_value);
+            mask(&mut difference.to_value);
+        }
+    }
+
+    if output_settings.output != Output::Table {
+        serialize_response(differences, output_settings);
+        return Ok(());
+    }
+
+    let from_project = client
+        .projects()
+        .get(&ProjectGetRequest {
+            id: from_project_id,
+        })
+        .await?;
+    let to_project = client
+        .projects()
+        .get(&ProjectGetRequest { id: to_project_id })
+        .await?;
+
+    print!(
+        "{}",
+        unified_diff(
+            &from_project.name,
+            &to_project.name,
+            &differences,
+            output_settings.color,
+        )
+    );
+
+    Ok(())
+}
+
+/// Compares the secrets of two projects by key, returning the differences sorted by key.
+fn compare(from: &[SecretResponse], to: &[SecretResponse]) -> Result<Vec<SecretDiff>> {
+    let from = values_by_key(from)?;
+    let to = values_by_key(to)?;
+
+    let keys: Vec<&str> = from
+        .keys()
+        .chain(to.keys())
+        .copied()
+        .sorted()
+        .dedup()
+        .collect();
+    Ok(keys
+        .into_iter()
+        .filter_map(|key| {
+            let (from_value, to_value) = (from.get(key), to.get(key));
+            let change = match (from_value, to_value) {
+                (None, _) => Change::Added,
+                (_, None) => Change::Removed,
+                (Some(a), Some(b)) if a != b => Change::Changed,
+                _ => return None,
+            };
+
+            Some(SecretDiff {
+                key: key.to_owned(),
+                change,
+                from_value: from_value.map(|v| v.to_string()),
+                to_value: to_value.map(|v| v.to_string()),
+            })
+        })
+        .collect())
+}
+
+fn values_by_key(secrets: &[SecretResponse]) -> Result<BTreeMap<&str, &str>> {
+    if let Some(duplicate) = secrets.iter().map(|s| &s.key).duplicates().next() {
+        bail!(
+            "Multiple secrets with name: '{}'. Use unique names for secrets to compare projects",
+            duplicate
+        );
+    }
+
+    Ok(secrets
+        .iter()
+        .map(|s| (s.key.as_str(), s.value.as_str()))
+        .collect())
+}
+
+fn mask(value: &mut Option<String>) {
+    if let Some(value) = value {
+        *value = MASKED_VALUE.to_owned();
+    }
+}
+
+/// Formats the differences like `diff -u`, one `KEY=VALUE` line per secret.
+fn unified_diff(from: &str, to: &str, differences: &[SecretDiff], color: Color) -> String {
+    let line = |sign: char, key: &str, value: &str| {
+        // Keep one line per secret, so multi-line values don't look like other secrets
+        let text = format!("{}{}={}", sign, key, value.replace('\n', "\\n"));
+        match (color.is_enabled(), sign) {
+            (true, '-') => format!("\x1b[31m{}\x1b[0m\n", text),
+            (true, '+') => format!("\x1b[32m{}\x1b[0m\n", text),
+            _ => format!("{}\n", text),
+        }
+    };
+
+    let mut output = format!("--- {}\n+++ {}\n", from, to);
+    for difference in differences {
+        if let Some(value) = &difference.from_value {
+            output.push_str(&line('-', &difference.key, value));
+        }
+        if let Some(value) = &difference.to_value {
+            output.push_str(&line('+', &difference.key, value));
+        }
+    }
+
+    output
+}
+
+impl TableSerialize<4> for SecretDiff {
+    fn get_headers() -> [&'static str; 4] {
+        ["Key", "Change", "From Value", "To Value"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 4]> {
+        let change = match self.change {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Changed => "changed",
+        };
+
+        vec![[
+            self.key.clone(),
+            change.to_owned(),
+            self.from_value.clone().unwrap_or_default(),
+            self.to_value.clone().unwrap_or_default(),
+        ]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::secret;
+
+    #[test]
+    fn compare_by_key() {
+        let from = [
+            secret("SAME", "1"),
+            secret("CHANGED", "a"),
+            secret("GONE", "x"),
+        ];
+        let to = [
+            secret("SAME", "1"),
+            secret("CHANGED", "b"),
+            secret("NEW", "y"),
+        ];
+
+        let differences = compare(&from, &to).unwrap();
+        let changes: Vec<(&str, Change)> = differences
+            .iter()
+            .map(|d| (d.key.as_str(), d.change))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("CHANGED", Change::Changed),
+                ("GONE", Change::Removed),
+                ("NEW", Change::Added),
+            ],
+            changes
+        );
+        assert_eq!(None, differences[2].from_value);
+        assert_eq!(Some("y".to_owned()), differences[2].to_value);
+    }
+
+    #[test]
+    fn compare_rejects_duplicate_keys() {
+        assert!(compare(&[secret("KEY", "a"), secret("KEY", "b")], &[]).is_err());
+    }
+
+    #[test]
+    fn unified_diff_lines() {
+        let differences = compare(
+            &[secret("CHANGED", "a\nb"), secret("GONE", "x")],
+            &[secret("CHANGED", "c")],
+        )
+        .unwrap();
+
+        assert_eq!(
+            "--- staging\n+++ prod\n-CHANGED=a\\nb\n+CHANGED=c\n-GONE=x\n",
+            unified_diff("staging", "prod", &differences, Color::No)
+        );
+    }
+}
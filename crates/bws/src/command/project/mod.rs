@@ -0,0 +1,289 @@
+mod clone;
+mod diff;
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient,
+        projects::{
+            ProjectCreateRequest, ProjectGetRequest, ProjectPutRequest, ProjectResponse,
+            ProjectsDeleteRequest, ProjectsListRequest,
+        },
+    },
+};
+use clone::ProjectCloneCommandModel;
+use color_eyre::eyre::{Result, bail};
+use diff::ProjectDiffCommandModel;
+use uuid::Uuid;
+
+use crate::{
+    ProjectCommand,
+    render::{OutputSettings, serialize_response},
+};
+
+pub(crate) async fn process_command(
+    command: ProjectCommand,
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    match command {
+        ProjectCommand::List => list(client, organization_id, output_settings).await,
+        ProjectCommand::Get { project_id } => {
+            get(client, organization_id, project_id, output_settings).await
+        }
+        ProjectCommand::Clone {
+            project_id,
+            name,
+            blank_values,
+            rename_prefix,
+        } => {
+            clone::clone(
+                client,
+                organization_id,
+                ProjectCloneCommandModel {
+                    project_id,
+                    name,
+                    blank_values,
+                    rename_prefixes: rename_prefix,
+                },
+                output_settings,
+            )
+            .await
+        }
+        ProjectCommand::Create { name } => {
+            create(client, organization_id, name, output_settings).await
+        }
+        ProjectCommand::Edit { project_id, name } => {
+            edit(client, organization_id, project_id, name, output_settings).await
+        }
+        ProjectCommand::Delete { project_ids } => {
+            delete(client, organization_id, project_ids).await
+        }
+        ProjectCommand::Diff {
+            from_project_id,
+            to_project_id,
+            show_values,
+        } => {
+            diff::diff(
+                client,
+                organization_id,
+                ProjectDiffCommandModel {
+                    from_project_id,
+                    to_project_id,
+                    show_values,
+                },
+                output_settings,
+            )
+            .await
+        }
+    }
+}
+
+/// Resolves project IDs or names to project IDs. Names are looked up among the projects
+/// accessible by the machine account.
+pub(crate) async fn resolve_project_ids(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    projects: &[String],
+) -> Result<Vec<Uuid>> {
+    let existing = match projects.iter().any(|p| Uuid::parse_str(p).is_err()) {
+        true => {
+            client
+                .projects()
+                .list(&ProjectsListRequest { organization_id })
+                .await?
+                .data
+        }
+        false => Vec::new(),
+    };
+
+    projects
+        .iter()
+        .map(|project| match Uuid::parse_str(project) {
+            Ok(id) => Ok(id),
+            Err(_) => find_project_id(&existing, project),
+        })
+        .collect()
+}
+
+pub(crate) async fn resolve_project_id(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    project: &str,
+) -> Result<Uuid> {
+    Ok(resolve_project_ids(client, organization_id, &[project.to_owned()]).await?[0])
+}
+
+/// Like [resolve_project_id], for commands where the project is optional.
+pub(crate) async fn resolve_optional_project_id(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    project: Option<&str>,
+) -> Result<Option<Uuid>> {
+    match project {
+        Some(project) => Ok(Some(
+            resolve_project_id(client, organization_id, project).await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn find_project_id(projects: &[ProjectResponse], name: &str) -> Result<Uuid> {
+    let matches: Vec<Uuid> = projects
+        .iter()
+        .filter(|p| p.name == name)
+        .map(|p| p.id)
+        .collect();
+
+    match matches.as_slice() {
+        [id] => Ok(*id),
+        [] => bail!("Project '{}' not found", name),
+        _ => bail!(
+            "Multiple projects with name: '{}'. Use the project ID or use unique names for projects",
+            name
+        ),
+    }
+}
+
+pub(crate) async fn list(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let projects = client
+        .projects()
+        .list(&ProjectsListRequest {
+            organization_id: organization_id.into(),
+        })
+        .await?
+        .data;
+    serialize_response(projects, output_settings);
+
+    Ok(())
+}
+
+pub(crate) async fn get(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    project_id: String,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let project_id = resolve_project_id(&client, organization_id.into(), &project_id).await?;
+    let project = client
+        .projects()
+        .get(&ProjectGetRequest { id: project_id })
+        .await?;
+    serialize_response(project, output_settings);
+
+    Ok(())
+}
+
+pub(crate) async fn create(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    name: String,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let project = client
+        .projects()
+        .create(&ProjectCreateRequest {
+            organization_id: organization_id.into(),
+            name,
+        })
+        .await?;
+    serialize_response(project, output_settings);
+
+    Ok(())
+}
+
+pub(crate) async fn edit(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    project_id: String,
+    name: String,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let project_id = resolve_project_id(&client, organization_id, &project_id).await?;
+    let project = client
+        .projects()
+        .update(&ProjectPutRequest {
+            id: project_id,
+            organization_id,
+            name,
+        })
+        .await?;
+    serialize_response(project, output_settings);
+
+    Ok(())
+}
+
+pub(crate) async fn delete(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    project_ids: Vec<String>,
+) -> Result<()> {
+    let project_ids = resolve_project_ids(&client, organization_id.into(), &project_ids).await?;
+    let count = project_ids.len();
+
+    let result = client
+        .projects()
+        .delete(ProjectsDeleteRequest { ids: project_ids })
+        .await?;
+
+    let projects_failed: Vec<(Uuid, String)> = result
+        .data
+        .into_iter()
+        .filter_map(|r| r.error.map(|e| (r.id, e)))
+        .collect();
+    let deleted_projects = count - projects_failed.len();
+
+    match deleted_projects {
+        2.. => println!("{} projects deleted successfully.", deleted_projects),
+        1 => println!("{} project deleted successfully.", deleted_projects),
+        _ => (),
+    }
+
+    match projects_failed.len() {
+        2.. => eprintln!("{} projects had errors:", projects_failed.len()),
+        1 => eprintln!("{} project had an error:", projects_failed.len()),
+        _ => (),
+    }
+
+    for project in &projects_failed {
+        eprintln!("{}: {}", project.0, project.1);
+    }
+
+    if !projects_failed.is_empty() {
+        bail!("Errors when attempting to delete projects.");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::project;
+
+    #[test]
+    fn find_project_id_by_name() {
+        let projects = vec![project("prod-api"), project("staging"), project("staging")];
+
+        assert_eq!(
+            projects[0].id,
+            find_project_id(&projects, "prod-api").unwrap()
+        );
+        assert_eq!(
+            "Project 'dev' not found",
+            find_project_id(&projects, "dev").unwrap_err().to_string()
+        );
+        assert!(
+            find_project_id(&projects, "staging")
+                .unwrap_err()
+                .to_string()
+                .starts_with("Multiple projects with name: 'staging'")
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE},
+};
+use color_eyre::eyre::Result;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::Child,
+    task::JoinHandle,
+};
+
+const MASK: &[u8] = b"***";
+
+// Shorter values would mask unrelated parts of the output
+const MIN_MASKED_LEN: usize = 3;
+
+// How long the output is still copied for once the command has exited
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// How long output that could be the start of a value is held back for when nothing else is
+// printed, such as a prompt waiting for input
+const IDLE_FLUSH_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub(crate) struct OutputRedaction {
+    masks: SharedMasks,
+    copies: Vec<JoinHandle<Result<()>>>,
+}
+
+impl OutputRedaction {
+    pub(crate) fn new(secrets: &[(String, String)]) -> Result<Self> {
+        let mut masks = Masks::default();
+        masks.add(secrets)?;
+
+        Ok(OutputRedaction {
+            masks: Arc::new(Mutex::new(masks)),
+            copies: Vec::new(),
+        })
+    }
+
+    // Values are never removed, as the command may still print one it read before it changed
+    pub(crate) fn add(&self, secrets: &[(String, String)]) -> Result<()> {
+        self.masks
+            .lock()
+            .expect("masks lock is poisoned")
+            .add(secrets)
+    }
+
+    pub(crate) fn attach(&mut self, child: &mut Child) {
+        if let Some(stdout) = child.stdout.take() {
+            self.copies.push(redact_output(
+                stdout,
+                tokio::io::stdout(),
+                self.masks.clone(),
+            ));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.copies.push(redact_output(
+                stderr,
+                tokio::io::stderr(),
+                self.masks.clone(),
+            ));
+        }
+    }
+
+    pub(crate) async fn finish(self) {
+        // Processes left running in the background can keep the output open
+        let deadline = tokio::time::Instant::now() + DRAIN_TIMEOUT;
+        let mut stopped = false;
+        for mut copy in self.copies {
+            match tokio::time::timeout_at(deadline, &mut copy).await {
+                Ok(Ok(Ok(()))) => (),
+                Ok(Ok(Err(e))) => eprintln!("Warning: failed to copy the command output: {}", e),
+                Ok(Err(e)) => eprintln!("Warning: failed to copy the command output: {}", e),
+                Err(_) => {
+                    copy.abort();
+                    stopped = true;
+                }
+            }
+        }
+
+        let _ = tokio::io::stdout().flush().await;
+        let _ = tokio::io::stderr().flush().await;
+        if stopped {
+            eprintln!(
+                "Warning: stopped copying the command output, which is still open after the command exited"
+            );
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Masks {
+    // Sorted, to look up the values starting with some output
+    values: Vec<Vec<u8>>,
+    searcher: Option<AhoCorasick>,
+    max_len: usize,
+    // Already warned about, as the masks are added to again on every change
+    too_short: HashSet<String>,
+}
+
+type SharedMasks = Arc<Mutex<Masks>>;
+
+impl Masks {
+    fn add(&mut self, secrets: &[(String, String)]) -> Result<()> {
+        for (name, value) in secrets {
+            if value.is_empty() {
+                continue;
+            }
+            if value.len() < MIN_MASKED_LEN {
+                if self.too_short.insert(name.clone()) {
+                    eprintln!(
+                        "Warning: the value of '{}' is too short to be masked in the output",
+                        name
+                    );
+                }
+                continue;
+            }
+
+            for form in [
+                value.clone(),
+                STANDARD.encode(value),
+                URL_SAFE.encode(value),
+                url_encode(value),
+            ] {
+                let form = form.into_bytes();
+                if let Err(index) = self.values.binary_search(&form) {
+                    self.values.insert(index, form);
+                }
+            }
+        }
+
+        self.max_len = self.values.iter().map(Vec::len).max().unwrap_or_default();
+        self.searcher = match self.values.is_empty() {
+            true => None,
+            false => Some(
+                AhoCorasick::builder()
+                    .match_kind(MatchKind::LeftmostLongest)
+                    .build(&self.values)?,
+            ),
+        };
+
+        Ok(())
+    }
+
+    fn is_partial(&self, output: &[u8]) -> bool {
+        // The values starting with the output sort right after it
+        let index = self.values.partition_point(|v| v.as_slice() <= output);
+        self.values
+            .get(index)
+            .is_some_and(|v| v.starts_with(output))
+    }
+
+    fn partial_start(&self, output: &[u8]) -> usize {
+        // Only bytes shorter than the longest value can be its start
+        let window = output.len().saturating_sub(self.max_len.saturating_sub(1));
+        (window..output.len())
+            .find(|&start| self.is_partial(&output[start..]))
+            .unwrap_or(output.len())
+    }
+}
+
+// Percent-encodes everything but the unreserved characters of RFC 3986
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct Redactor {
+    masks: SharedMasks,
+    pending: Vec<u8>,
+    // How many of the pending bytes were already printed as a mask when the stream went idle
+    masked: usize,
+}
+
+impl Redactor {
+    fn new(masks: SharedMasks) -> Self {
+        Redactor {
+            masks,
+            pending: Vec::new(),
+            masked: 0,
+        }
+    }
+
+    fn redact(&mut self, data: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(data);
+        self.redact_pending(false)
+    }
+
+    fn has_unprinted(&self) -> bool {
+        self.pending.len() > self.masked
+    }
+
+    // The held back bytes may be the start of a value, so they're printed as a mask and kept to
+    // tell whether the output that follows is the rest of it
+    fn flush_idle(&mut self) -> Vec<u8> {
+        self.masked = self.pending.len();
+        MASK.to_vec()
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        self.redact_pending(true)
+    }
+
+    fn redact_pending(&mut self, at_end: bool) -> Vec<u8> {
+        let masks = self.masks.lock().expect("masks lock is poisoned");
+        let masked = std::mem::take(&mut self.masked);
+        let Some(searcher) = &masks.searcher else {
+            return self.pending.drain(..).skip(masked).collect();
+        };
+        let data = &self.pending;
+
+        let held = match at_end {
+            true => data.len(),
+            false => masks.partial_start(data),
+        };
+
+        // Bytes already printed as a mask are skipped, along with the values starting in them
+        let mut output = Vec::with_capacity(data.len());
+        let print = |output: &mut Vec<u8>, start: usize, end: usize| {
+            output.extend_from_slice(&data[start.max(masked).min(end)..end]);
+        };
+        let mut position = 0;
+        for value in searcher.find_iter(data.as_slice()) {
+            if value.start() >= held {
+                break;
+            }
+            print(&mut output, position, value.start());
+            if value.start() >= masked {
+                output.extend_from_slice(MASK);
+            }
+            position = value.end();
+        }
+
+        // A value can run past the held back bytes, in which case what's left of them is still
+        // held back until the next read
+        let position = match position < held {
+            true => {
+                print(&mut output, position, held);
+                held
+            }
+            false => position,
+        };
+
+        self.pending.drain(..position);
+        self.masked = masked.saturating_sub(position);
+        output
+    }
+}
+
+fn redact_output(
+    mut reader: impl AsyncRead + Unpin + Send + 'static,
+    mut writer: impl AsyncWrite + Unpin + Send + 'static,
+    masks: SharedMasks,
+) -> JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        let mut redactor = Redactor::new(masks);
+        let mut buffer = vec![0; 8192];
+
+        loop {
+            // Output held back is masked when nothing follows it for a while, as the command may
+            // be waiting for input after printing it
+            let read = match redactor.has_unprinted() {
+                true => {
+                    match tokio::time::timeout(IDLE_FLUSH_TIMEOUT, reader.read(&mut buffer)).await {
+                        Ok(read) => Some(read?),
+                        Err(_) => None,
+                    }
+                }
+                false => Some(reader.read(&mut buffer).await?),
+            };
+            let output = match read {
+                Some(0) => redactor.finish(),
+                Some(read) => redactor.redact(&buffer[..read]),
+                None => redactor.flush_idle(),
+            };
+
+            writer.write_all(&output).await?;
+            writer.flush().await?;
+
+            if read == Some(0) {
+                return Ok(());
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn masks(values: &[&str]) -> SharedMasks {
+        let mut masks = Masks::default();
+        masks
+            .add(
+                &values
+                    .iter()
+                    .map(|v| ("SECRET".to_owned(), v.to_string()))
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+        Arc::new(Mutex::new(masks))
+    }
+
+    fn redact_chunks(masks: SharedMasks, chunks: &[&str]) -> String {
+        let mut redactor = Redactor::new(masks);
+        let mut output = Vec::new();
+        for chunk in chunks {
+            output.extend(redactor.redact(chunk.as_bytes()));
+        }
+        output.extend(redactor.finish());
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn redacts_values_and_their_encodings() {
+        let masks = masks(&["hunter2", "p@ss word"]);
+
+        assert_eq!(
+            "password=*** token=*** url=x://u:***@host",
+            redact_chunks(
+                masks,
+                &["password=hunter2 token=aHVudGVyMg== url=x://u:p%40ss%20word@host"]
+            )
+        );
+    }
+
+    #[test]
+    fn redacts_values_split_across_reads() {
+        let masks = masks(&["hunter2"]);
+
+        assert_eq!(
+            "key=*** and ***\n",
+            redact_chunks(masks.clone(), &["key=hun", "te", "r2 and h", "unter2\n"])
+        );
+        // A partial value is only held back until it's known not to be the secret
+        assert_eq!(
+            "key=hunt\n",
+            redact_chunks(masks.clone(), &["key=hun", "t\n"])
+        );
+        assert_eq!("key=hun", redact_chunks(masks, &["key=hun"]));
+    }
+
+    #[test]
+    fn redacts_the_longest_value() {
+        let masks = masks(&["abc", "cde", "hunter2", "hunter2xyz"]);
+
+        assert_eq!("***de", redact_chunks(masks.clone(), &["abcd", "e"]));
+        assert_eq!(
+            "*** and ***",
+            redact_chunks(masks, &["hunter2x", "yz and hunter2"])
+        );
+    }
+
+    #[test]
+    fn masks_partial_values_when_the_output_is_idle() {
+        let masks = masks(&["hunter2"]);
+
+        let mut redactor = Redactor::new(masks.clone());
+        let mut output = redactor.redact(b"key=hun");
+        output.extend(redactor.flush_idle());
+        output.extend(redactor.redact(b"ter2 and hunt"));
+        output.extend(redactor.finish());
+        assert_eq!("key=*** and hunt", String::from_utf8(output).unwrap());
+
+        let mut redactor = Redactor::new(masks);
+        let mut output = redactor.redact(b"key=hun");
+        output.extend(redactor.flush_idle());
+        output.extend(redactor.redact(b"t\n"));
+        output.extend(redactor.finish());
+        assert_eq!("key=***t\n", String::from_utf8(output).unwrap());
+    }
+
+    #[tokio::test]
+    async fn values_written_after_a_pause_are_masked() {
+        let (mut command, reader) = tokio::io::duplex(64);
+        let (writer, mut output) = tokio::io::duplex(64);
+        let copy = redact_output(reader, writer, masks(&["hunter2"]));
+
+        // The prompt isn't held back while the command waits
+        command.write_all(b"Password: hun").await.unwrap();
+        let mut buffer = vec![0; 64];
+        let mut printed = Vec::new();
+        while printed.len() < 13 {
+            let read = tokio::time::timeout(DRAIN_TIMEOUT, output.read(&mut buffer))
+                .await
+                .expect("the prompt was held back")
+                .unwrap();
+            printed.extend_from_slice(&buffer[..read]);
+        }
+        assert_eq!(b"Password: ***", printed.as_slice());
+
+        tokio::time::sleep(IDLE_FLUSH_TIMEOUT * 2).await;
+        command.write_all(b"ter2\n").await.unwrap();
+        drop(command);
+        copy.await.unwrap().unwrap();
+
+        output.read_to_end(&mut printed).await.unwrap();
+        assert_eq!(b"Password: ***\n", printed.as_slice());
+    }
+
+    #[tokio::test]
+    async fn finish_stops_copying_output_that_stays_open() {
+        let (_writer, reader) = tokio::io::duplex(64);
+        let redaction = OutputRedaction {
+            masks: masks(&[]),
+            copies: vec![redact_output(reader, tokio::io::sink(), masks(&[]))],
+        };
+
+        tokio::time::timeout(DRAIN_TIMEOUT * 2, redaction.finish())
+            .await
+            .expect("finish waited for the output to be closed");
+    }
+
+    #[test]
+    fn short_values_are_not_masked() {
+        let masks = masks(&["", "ab"]);
+
+        assert_eq!("ab", redact_chunks(masks, &["ab"]));
+    }
+
+    #[test]
+    fn short_values_are_warned_about_once() {
+        let mut masks = Masks::default();
+        let secrets = [("SHORT".to_owned(), "ab".to_owned())];
+
+        masks.add(&secrets).unwrap();
+        masks.add(&secrets).unwrap();
+
+        assert_eq!(HashSet::from(["SHORT".to_owned()]), masks.too_short);
+        assert!(masks.searcher.is_none());
+    }
+}
@@ -0,0 +1,752 @@
+mod mask;
+mod mount;
+mod process;
+mod select;
+mod watch;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{IsTerminal, Read},
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{SecretsManagerClient, secrets::SecretResponse},
+};
+use chrono::Utc;
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use mask::OutputRedaction;
+use mount::SecretsDir;
+use process::{TerminationSignals, forward_signal, is_shutdown_signal, kill_child, stop_child};
+use select::SecretSelection;
+use tokio::{
+    process::{Child, Command},
+    time::Instant,
+};
+use uuid::Uuid;
+use watch::SecretsWatcher;
+use which::which;
+
+use crate::{
+    ACCESS_TOKEN_KEY_VAR_NAME, MOUNT_DIR_VAR_NAME, OnChange, StdinMode,
+    cache::SecretsCache,
+    command::{project::resolve_project_ids, secret::get_secrets},
+    naming::NameTransform,
+    util::{is_valid_posix_name, uuid_to_posix},
+};
+
+// Essential environment variables that should be preserved even when `--no-inherit-env` is used
+const WINDOWS_ESSENTIAL_VARS: &[&str] = &["SystemRoot", "ComSpec", "windir"];
+
+#[derive(Debug)]
+pub(crate) struct RunCommandModel {
+    pub(crate) command: Vec<String>,
+    pub(crate) shell: Option<String>,
+    pub(crate) no_shell: bool,
+    pub(crate) stdin: Option<StdinMode>,
+    pub(crate) no_inherit_env: bool,
+    pub(crate) project_ids: Vec<String>,
+    pub(crate) prefix: Option<String>,
+    pub(crate) strip_prefix: Option<String>,
+    pub(crate) key_names: NameTransform,
+    pub(crate) only: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) uuids_as_keynames: bool,
+    pub(crate) mount_dir: bool,
+    pub(crate) watch: bool,
+    pub(crate) watch_interval: u64,
+    pub(crate) on_change: OnChange,
+    pub(crate) watch_signal: String,
+    pub(crate) stop_timeout: u64,
+    pub(crate) mask_output: bool,
+    pub(crate) cache: Option<SecretsCache>,
+}
+
+pub(crate) async fn run(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    run: RunCommandModel,
+) -> Result<i32> {
+    let RunCommandModel {
+        command,
+        shell,
+        no_shell,
+        stdin,
+        no_inherit_env,
+        project_ids,
+        prefix,
+        strip_prefix,
+        key_names,
+        only,
+        exclude,
+        uuids_as_keynames,
+        mount_dir,
+        watch,
+        watch_interval,
+        on_change,
+        watch_signal,
+        stop_timeout,
+        mask_output,
+        cache,
+    } = run;
+    let stop_timeout = Duration::from_secs(stop_timeout);
+    let organization_id: Uuid = organization_id.into();
+
+    if watch && on_change == OnChange::Signal && !mount_dir {
+        bail!(
+            "--on-change signal requires --mount-dir, as the environment of a running process can't be updated"
+        );
+    }
+
+    #[cfg(unix)]
+    let watch_signal = process::parse_signal(&watch_signal)?;
+    #[cfg(not(unix))]
+    if watch && on_change == OnChange::Signal {
+        bail!("Sending {} is only supported on Unix systems", watch_signal);
+    }
+
+    let stdin = stdin_mode(stdin, &command, no_shell)?;
+
+    // A command reading from the terminal stays in its foreground process group, so it can use it
+    // like when run directly
+    let interactive = stdin == StdinMode::Inherit && std::io::stdin().is_terminal();
+
+    let program = match no_shell {
+        true => Program::direct(command)?,
+        false => Program::shell(shell, command)?,
+    };
+
+    let synced_at = Utc::now();
+    let request = serde_json::json!(["run", project_ids]).to_string();
+    let fetch = async {
+        let project_ids =
+            last_occurrences(resolve_project_ids(&client, organization_id, &project_ids).await?);
+        let secrets = project_secrets(&client, organization_id, &project_ids).await?;
+
+        Ok((project_ids, secrets))
+    };
+
+    let (project_ids, secrets) = match &cache {
+        Some(cache) => cache.cached(organization_id, &request, fetch).await?,
+        None => fetch.await?,
+    };
+
+    let naming = Naming {
+        project_ids: project_ids.clone(),
+        uuids_as_keynames,
+        prefix,
+        strip_prefix,
+        key_names,
+    };
+
+    let selection = SecretSelection::new(&only, &exclude)?;
+
+    let mut secrets = named_secrets(selection.select(secrets)?, &naming)?;
+
+    // When mounting, the secrets are written to files and only the directory path is exposed
+    let secrets_dir = match mount_dir {
+        true => {
+            let secrets_dir = SecretsDir::new()?;
+            secrets_dir.write_all(&secrets)?;
+            Some(secrets_dir)
+        }
+        false => None,
+    };
+
+    let mut watcher = watch.then(|| {
+        SecretsWatcher::new(
+            &client,
+            organization_id,
+            project_ids,
+            Duration::from_secs(watch_interval),
+            synced_at,
+        )
+    });
+
+    let mut redaction = mask_output
+        .then(|| OutputRedaction::new(&secrets))
+        .transpose()?;
+
+    let environment = build_environment(&secrets, secrets_dir.as_ref());
+
+    // Without a shell and with nothing to supervise, the command takes the place of `bws`, so it
+    // receives signals directly and can run as PID 1 in containers
+    #[cfg(unix)]
+    if matches!(program, Program::Direct(_)) && secrets_dir.is_none() && !watch && !mask_output {
+        use std::os::unix::process::CommandExt;
+
+        let e = build_command(&program, stdin, no_inherit_env, environment).exec();
+        bail!("Failed to execute process: {}", e);
+    }
+
+    // Termination signals are forwarded to the child, and `bws` keeps running until it exits
+    let mut signals = TerminationSignals::new()?;
+    let mut kill_at: Option<Instant> = None;
+    let mut child = spawn(
+        build_command(&program, stdin, no_inherit_env, environment),
+        interactive,
+        redaction.as_mut(),
+    )?;
+
+    // propagate the exit status from the child process
+    let exit_code = loop {
+        tokio::select! {
+            exit_status = child.wait() => break exit_code(exit_status),
+            signal = signals.recv() => {
+                let forwarded = forward_signal(&child, signal)?;
+
+                // The command is shutting down, so changes to the secrets no longer apply. It
+                // keeps running after a SIGHUP, and handles a Ctrl-C it got from the terminal
+                // itself
+                if forwarded && is_shutdown_signal(signal) {
+                    watcher = None;
+                    kill_at.get_or_insert_with(|| Instant::now() + stop_timeout);
+                }
+            }
+            _ = deadline(kill_at) => {
+                eprintln!("The command did not exit in time, killing it");
+                kill_child(&mut child)?;
+                kill_at = None;
+            }
+            updated_secrets = changed(watcher.as_mut()) => {
+                let updated_secrets = selection
+                    .select(updated_secrets)
+                    .and_then(|updated_secrets| named_secrets(updated_secrets, &naming));
+                let updated_secrets = match updated_secrets {
+                    Ok(updated_secrets) if updated_secrets != secrets => updated_secrets,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        eprintln!("Warning: {} Keeping the current secrets.", e);
+                        continue;
+                    }
+                };
+
+                if let Some(secrets_dir) = &secrets_dir {
+                    secrets_dir.write_all(&updated_secrets)?;
+                }
+                if let Some(redaction) = &redaction {
+                    redaction.add(&updated_secrets)?;
+                }
+
+                match on_change {
+                    OnChange::Restart => {
+                        eprintln!("Secrets changed, restarting the command");
+                        stop_child(&mut child, stop_timeout).await?;
+
+                        let environment =
+                            build_environment(&updated_secrets, secrets_dir.as_ref());
+                        child = spawn(
+                            build_command(&program, stdin, no_inherit_env, environment),
+                            interactive,
+                            redaction.as_mut(),
+                        )?;
+                    }
+                    OnChange::Signal => {
+                        #[cfg(unix)]
+                        process::send_signal(&child, watch_signal)?;
+                    }
+                }
+
+                secrets = updated_secrets;
+            }
+        }
+    };
+
+    if let Some(redaction) = redaction {
+        redaction.finish().await;
+    }
+
+    // Remove the secret files before returning, as the caller exits the process right away
+    drop(secrets_dir);
+
+    exit_code
+}
+
+/// Retrieves the secrets of the projects, in the order the projects were given, or all the secrets
+/// accessible by the machine account when no project is given.
+async fn project_secrets(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    project_ids: &[Uuid],
+) -> Result<Vec<SecretResponse>> {
+    if project_ids.is_empty() {
+        return get_secrets(client, organization_id, None).await;
+    }
+
+    let mut secrets = Vec::new();
+    for project_id in project_ids {
+        secrets.extend(get_secrets(client, organization_id, Some(*project_id)).await?);
+    }
+
+    Ok(secrets)
+}
+
+// A project given more than once takes the precedence of its last position
+fn last_occurrences(project_ids: Vec<Uuid>) -> Vec<Uuid> {
+    let mut project_ids: Vec<Uuid> = project_ids.into_iter().rev().unique().collect();
+    project_ids.reverse();
+    project_ids
+}
+
+/// How secrets are named in the environment of the command.
+struct Naming {
+    /// The projects in order of increasing precedence
+    project_ids: Vec<Uuid>,
+    uuids_as_keynames: bool,
+    prefix: Option<String>,
+    strip_prefix: Option<String>,
+    key_names: NameTransform,
+}
+
+impl Naming {
+    fn name(&self, secret: &SecretResponse) -> String {
+        if self.uuids_as_keynames {
+            return format!(
+                "{}{}",
+                self.prefix.as_deref().unwrap_or_default(),
+                uuid_to_posix(&secret.id)
+            );
+        }
+
+        // Keys are transformed after removing the prefix, so that the prefix is given as it
+        // appears in the keys, and before adding the prefix, which is used as is
+        let key = match &self.strip_prefix {
+            Some(strip_prefix) => secret.key.strip_prefix(strip_prefix.as_str()),
+            None => None,
+        };
+
+        format!(
+            "{}{}",
+            self.prefix.as_deref().unwrap_or_default(),
+            self.key_names.apply(key.unwrap_or(&secret.key))
+        )
+    }
+
+    /// Secrets of projects given later take precedence over the ones given earlier.
+    fn precedence(&self, secret: &SecretResponse) -> usize {
+        secret
+            .project_id
+            .and_then(|id| self.project_ids.iter().position(|p| *p == id))
+            .unwrap_or_default()
+    }
+}
+
+/// Pairs every secret value with the name it's exposed under, sorted by name. When secrets of
+/// different projects end up with the same name, the project with the highest precedence wins.
+fn named_secrets(secrets: Vec<SecretResponse>, naming: &Naming) -> Result<Vec<(String, String)>> {
+    if !naming.uuids_as_keynames {
+        if let Some((_, duplicate)) = secrets
+            .iter()
+            .map(|s| (naming.precedence(s), &s.key))
+            .duplicates()
+            .next()
+        {
+            bail!(
+                "Multiple secrets with name: '{}'. Use --uuids-as-keynames or use unique names for secrets",
+                duplicate
+            );
+        }
+    }
+
+    let mut named: BTreeMap<String, (usize, String, String)> = BTreeMap::new();
+    for secret in secrets.into_iter().sorted_by_key(|s| naming.precedence(s)) {
+        let name = naming.name(&secret);
+        let precedence = naming.precedence(&secret);
+
+        if let Some((other_precedence, other_key, _)) = named.get(&name)
+            && *other_precedence == precedence
+        {
+            bail!(
+                "Secrets '{}' and '{}' would both be named '{}'",
+                other_key,
+                secret.key,
+                name
+            );
+        }
+        named.insert(name, (precedence, secret.key, secret.value));
+    }
+
+    Ok(named
+        .into_iter()
+        .map(|(name, (_, _, value))| (name, value))
+        .collect())
+}
+
+fn build_environment(
+    secrets: &[(String, String)],
+    secrets_dir: Option<&SecretsDir>,
+) -> HashMap<String, String> {
+    if let Some(secrets_dir) = secrets_dir {
+        let path = secrets_dir.path().to_string_lossy().into_owned();
+        return HashMap::from([(MOUNT_DIR_VAR_NAME.to_owned(), path)]);
+    }
+
+    secrets
+        .iter()
+        .inspect(|(k, _)| {
+            if !is_valid_posix_name(k) {
+                eprintln!(
+                    "Warning: secret '{}' does not have a POSIX-compliant name",
+                    k
+                );
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Resolves the standard input of the command, which is where the script is read from when no
+/// command is given.
+fn stdin_mode(stdin: Option<StdinMode>, command: &[String], no_shell: bool) -> Result<StdinMode> {
+    let stdin = stdin.unwrap_or(match command.is_empty() {
+        true => StdinMode::Script,
+        false => StdinMode::Inherit,
+    });
+
+    match stdin {
+        StdinMode::Script if no_shell => bail!("--stdin script can't be used with --no-shell"),
+        StdinMode::Script if !command.is_empty() => {
+            bail!("--stdin script reads the command from stdin, so no command can be given")
+        }
+        StdinMode::Inherit | StdinMode::Null if command.is_empty() => bail!("No command provided"),
+        _ => Ok(stdin),
+    }
+}
+
+/// The process started by `run`.
+enum Program {
+    /// A command line run by a shell
+    Shell { shell: String, command: String },
+    /// A program and its arguments, run without a shell
+    Direct(Vec<String>),
+}
+
+impl Program {
+    fn shell(shell: Option<String>, command: Vec<String>) -> Result<Self> {
+        let shell = shell.unwrap_or_else(|| {
+            if std::env::consts::OS == "windows" {
+                "powershell".to_string()
+            } else {
+                "sh".to_string()
+            }
+        });
+
+        if which(&shell).is_err() {
+            bail!("Shell '{}' not found", shell);
+        }
+
+        let command = if command.is_empty() {
+            if std::io::stdin().is_terminal() {
+                bail!("No command provided");
+            }
+
+            let mut buffer = String::new();
+            std::io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        } else {
+            command.join(" ")
+        };
+
+        Ok(Program::Shell { shell, command })
+    }
+
+    fn direct(command: Vec<String>) -> Result<Self> {
+        if command.is_empty() {
+            bail!("No command provided");
+        }
+
+        Ok(Program::Direct(command))
+    }
+}
+
+fn build_command(
+    program: &Program,
+    stdin: StdinMode,
+    no_inherit_env: bool,
+    environment: HashMap<String, String>,
+) -> std::process::Command {
+    let is_windows = std::env::consts::OS == "windows";
+
+    let mut command = match program {
+        Program::Shell { shell, command } => {
+            let mut shell = std::process::Command::new(shell);
+            shell.arg("-c").arg(command);
+            shell
+        }
+        Program::Direct(args) => {
+            let mut program = std::process::Command::new(&args[0]);
+            program.args(&args[1..]);
+            program
+        }
+    };
+    command
+        .stdin(match stdin {
+            StdinMode::Inherit => Stdio::inherit(),
+            // The script has already been read from stdin, leaving nothing for the command
+            StdinMode::Null | StdinMode::Script => Stdio::null(),
+        })
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    if no_inherit_env {
+        let path = std::env::var("PATH").unwrap_or_else(|_| match is_windows {
+            true => "C:\\Windows;C:\\Windows\\System32".to_string(),
+            false => "/bin:/usr/bin".to_string(),
+        });
+
+        command.env_clear();
+
+        // Preserve essential PowerShell environment variables on Windows
+        if is_windows {
+            for &var in WINDOWS_ESSENTIAL_VARS {
+                if let Ok(value) = std::env::var(var) {
+                    command.env(var, value);
+                }
+            }
+        }
+
+        command.env("PATH", path); // PATH is always necessary
+        command.envs(environment);
+    } else {
+        command.env_remove(ACCESS_TOKEN_KEY_VAR_NAME);
+        command.envs(environment);
+    }
+
+    command
+}
+
+fn spawn(
+    mut command: std::process::Command,
+    #[cfg_attr(not(unix), expect(unused_variables))] interactive: bool,
+    redaction: Option<&mut OutputRedaction>,
+) -> Result<Child> {
+    // Unless it needs the terminal, the child gets its own process group, so that signals reach
+    // the processes it starts too
+    #[cfg(unix)]
+    if !interactive {
+        use std::os::unix::process::CommandExt;
+
+        command.process_group(0);
+    }
+
+    if redaction.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = match Command::from(command).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            bail!("Failed to execute process: {}", e)
+        }
+    };
+
+    if let Some(redaction) = redaction {
+        redaction.attach(&mut child);
+    }
+
+    Ok(child)
+}
+
+fn exit_code(exit_status: std::io::Result<ExitStatus>) -> Result<i32> {
+    match exit_status {
+        Ok(exit_status) => {
+            // Like shells, report a command killed by a signal as 128 + the signal number
+            #[cfg(unix)]
+            if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&exit_status) {
+                return Ok(128 + signal);
+            }
+
+            Ok(exit_status.code().unwrap_or(1))
+        }
+        Err(e) => {
+            bail!("Failed to wait for process: {}", e)
+        }
+    }
+}
+
+async fn deadline(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn changed(watcher: Option<&mut SecretsWatcher<'_>>) -> Vec<SecretResponse> {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::project_secret;
+
+    #[test]
+    fn repeated_projects_keep_their_last_position() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+
+        assert_eq!(vec![b, a], last_occurrences(vec![a, b, a]));
+        assert_eq!(vec![a, b], last_occurrences(vec![a, b]));
+    }
+
+    fn naming(project_ids: &[Uuid]) -> Naming {
+        Naming {
+            project_ids: project_ids.to_vec(),
+            uuids_as_keynames: false,
+            prefix: None,
+            strip_prefix: None,
+            key_names: NameTransform::default(),
+        }
+    }
+
+    #[test]
+    fn later_projects_take_precedence() {
+        let (shared, service) = (Uuid::new_v4(), Uuid::new_v4());
+        let secrets = vec![
+            project_secret(service, "DB_HOST", "service-db"),
+            project_secret(shared, "DB_HOST", "shared-db"),
+            project_secret(shared, "LOG_LEVEL", "info"),
+        ];
+
+        assert_eq!(
+            vec![
+                ("DB_HOST".to_owned(), "service-db".to_owned()),
+                ("LOG_LEVEL".to_owned(), "info".to_owned()),
+            ],
+            named_secrets(secrets, &naming(&[shared, service])).unwrap()
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_in_a_project_are_rejected() {
+        let project = Uuid::new_v4();
+        let secrets = vec![
+            project_secret(project, "KEY", "a"),
+            project_secret(project, "KEY", "b"),
+        ];
+
+        assert!(named_secrets(secrets, &naming(&[project])).is_err());
+    }
+
+    #[test]
+    fn prefixes_are_stripped_and_added() {
+        let project = Uuid::new_v4();
+        let naming = Naming {
+            prefix: Some("MY_".to_owned()),
+            strip_prefix: Some("APP_".to_owned()),
+            ..naming(&[project])
+        };
+
+        assert_eq!(
+            vec![
+                ("MY_OTHER".to_owned(), "b".to_owned()),
+                ("MY_PORT".to_owned(), "a".to_owned()),
+            ],
+            named_secrets(
+                vec![
+                    project_secret(project, "APP_PORT", "a"),
+                    project_secret(project, "OTHER", "b")
+                ],
+                &naming
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            "Secrets 'APP_PORT' and 'PORT' would both be named 'MY_PORT'",
+            named_secrets(
+                vec![
+                    project_secret(project, "APP_PORT", "a"),
+                    project_secret(project, "PORT", "b")
+                ],
+                &naming
+            )
+            .unwrap_err()
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn keys_are_transformed_after_stripping_the_prefix() {
+        let project = Uuid::new_v4();
+        let naming = Naming {
+            prefix: Some("my_".to_owned()),
+            strip_prefix: Some("app.".to_owned()),
+            key_names: NameTransform::new(true, true, None).unwrap(),
+            ..naming(&[project])
+        };
+
+        assert_eq!(
+            vec![("my_DB_PASSWORD".to_owned(), "a".to_owned())],
+            named_secrets(
+                vec![project_secret(project, "app.db-password", "a")],
+                &naming
+            )
+            .unwrap()
+        );
+        assert!(
+            named_secrets(
+                vec![
+                    project_secret(project, "db.host", "a"),
+                    project_secret(project, "DB_HOST", "b")
+                ],
+                &naming
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn direct_commands_keep_their_arguments() {
+        let program = Program::direct(vec!["echo".to_owned(), "a  b".to_owned()]).unwrap();
+        let command = build_command(&program, StdinMode::Inherit, false, HashMap::new());
+
+        assert_eq!("echo", command.get_program());
+        assert_eq!(vec!["a  b"], command.get_args().collect::<Vec<_>>());
+        assert!(Program::direct(Vec::new()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signal_exit_codes() {
+        let status = std::process::Command::new("sh")
+            .args(["-c", "kill -TERM $$"])
+            .status();
+
+        assert_eq!(143, exit_code(status).unwrap());
+        assert_eq!(
+            3,
+            exit_code(
+                std::process::Command::new("sh")
+                    .args(["-c", "exit 3"])
+                    .status()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn stdin_mode_defaults_to_the_script_without_a_command() {
+        let command = vec!["psql".to_owned()];
+
+        assert_eq!(StdinMode::Script, stdin_mode(None, &[], false).unwrap());
+        assert_eq!(
+            StdinMode::Inherit,
+            stdin_mode(None, &command, false).unwrap()
+        );
+        assert_eq!(
+            StdinMode::Null,
+            stdin_mode(Some(StdinMode::Null), &command, true).unwrap()
+        );
+
+        assert!(stdin_mode(Some(StdinMode::Script), &command, false).is_err());
+        assert!(stdin_mode(Some(StdinMode::Script), &[], true).is_err());
+        assert!(stdin_mode(Some(StdinMode::Inherit), &[], false).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use color_eyre::eyre::{Result, bail};
+use tempfile::TempDir;
+
+use crate::util::{is_valid_file_name, scrub_file, secrets_temp_dir};
+
+const MOUNT_DIR_PREFIX: &str = "bws-";
+
+/// A private temporary directory holding one file per secret.
+///
+/// The directory is only accessible by the current user, and every file in it is overwritten with
+/// zeros before the directory is removed on drop.
+pub(crate) struct SecretsDir {
+    dir: TempDir,
+}
+
+impl SecretsDir {
+    pub(crate) fn new() -> Result<Self> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(MOUNT_DIR_PREFIX);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o700));
+        }
+
+        Ok(SecretsDir {
+            dir: builder.tempdir_in(secrets_temp_dir())?,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes one file per secret, replacing the contents of existing files and removing the
+    /// files of secrets that are no longer present.
+    pub(crate) fn write_all(&self, secrets: &[(String, String)]) -> Result<()> {
+        if let Some((name, _)) = secrets.iter().find(|(name, _)| !is_valid_file_name(name)) {
+            bail!(
+                "Secret '{}' can't be used as a file name. Use --uuids-as-keynames or rename the secret",
+                name
+            );
+        }
+
+        for (name, value) in secrets {
+            self.write(name, value)?;
+        }
+
+        for entry in fs::read_dir(self.dir.path())? {
+            let entry = entry?;
+            if !secrets
+                .iter()
+                .any(|(name, _)| entry.file_name() == name.as_str())
+            {
+                scrub_file(&entry.path())?;
+                fs::remove_file(entry.path())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, name: &str, value: &str) -> Result<()> {
+        let path = self.dir.path().join(name);
+
+        // Previous values are overwritten before the file is truncated, so that they don't linger
+        if path.exists() {
+            scrub_file(&path)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path)?;
+        file.write_all(value.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn scrub(&self) -> std::io::Result<()> {
+        for entry in fs::read_dir(self.dir.path())? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                scrub_file(&entry.path())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for SecretsDir {
+    fn drop(&mut self) {
+        // The directory itself is removed when `dir` is dropped, right after this
+        if let Err(e) = self.scrub() {
+            eprintln!(
+                "Warning: failed to overwrite secret files in {}: {}",
+                self.dir.path().display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_dir_is_removed_on_drop() {
+        let dir = SecretsDir::new().unwrap();
+        dir.write_all(&[("API_KEY".to_owned(), "hunter2".to_owned())])
+            .unwrap();
+
+        let path = dir.path().to_owned();
+        assert_eq!("hunter2", fs::read_to_string(path.join("API_KEY")).unwrap());
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secrets_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = SecretsDir::new().unwrap();
+        dir.write_all(&[("API_KEY".to_owned(), "hunter2".to_owned())])
+            .unwrap();
+
+        let dir_mode = fs::metadata(dir.path()).unwrap().permissions().mode();
+        let file_mode = fs::metadata(dir.path().join("API_KEY"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(0o700, dir_mode & 0o777);
+        assert_eq!(0o600, file_mode & 0o777);
+    }
+
+    #[test]
+    fn secrets_dir_replaces_previous_secrets() {
+        let dir = SecretsDir::new().unwrap();
+        dir.write_all(&[
+            ("API_KEY".to_owned(), "a much longer value".to_owned()),
+            ("OLD_KEY".to_owned(), "old".to_owned()),
+        ])
+        .unwrap();
+        dir.write_all(&[("API_KEY".to_owned(), "short".to_owned())])
+            .unwrap();
+
+        assert_eq!(
+            "short",
+            fs::read_to_string(dir.path().join("API_KEY")).unwrap()
+        );
+        assert!(!dir.path().join("OLD_KEY").exists());
+    }
+
+    #[test]
+    fn secrets_dir_rejects_paths() {
+        let dir = SecretsDir::new().unwrap();
+        assert!(
+            dir.write_all(&[("../API_KEY".to_owned(), "hunter2".to_owned())])
+                .is_err()
+        );
+        assert!(
+            dir.write_all(&[("..".to_owned(), "hunter2".to_owned())])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{Result, bail};
+use tokio::process::Child;
+
+#[cfg(unix)]
+pub(crate) fn parse_signal(name: &str) -> Result<nix::sys::signal::Signal> {
+    let name = name.trim().to_uppercase();
+    let name = match name.starts_with("SIG") {
+        true => name,
+        false => format!("SIG{name}"),
+    };
+
+    match name.parse() {
+        Ok(signal) => Ok(signal),
+        Err(_) => bail!("'{}' is not a valid signal", name),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn send_signal(child: &Child, signal: nix::sys::signal::Signal) -> Result<()> {
+    use nix::{
+        sys::signal::kill,
+        unistd::{Pid, getpgid},
+    };
+
+    let Some(pid) = child.id() else {
+        return Ok(());
+    };
+
+    // A child leading its own process group is signaled along with the processes it started
+    let pid = Pid::from_raw(pid as i32);
+    let target = match getpgid(Some(pid)) {
+        Ok(group) if group == pid => Pid::from_raw(-group.as_raw()),
+        _ => pid,
+    };
+
+    if let Err(e) = kill(target, signal) {
+        bail!("Failed to send {} to process: {}", signal, e);
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+pub(crate) fn forward_signal(child: &Child, signal: i32) -> Result<bool> {
+    use nix::{
+        sys::signal::Signal,
+        unistd::{Pid, getpgid},
+    };
+
+    let signal = Signal::try_from(signal)?;
+
+    // A child in the process group of `bws` shares its terminal, which already sent it the Ctrl-C
+    let shares_group = child
+        .id()
+        .is_some_and(|pid| getpgid(Some(Pid::from_raw(pid as i32))) == getpgid(None));
+    if signal == Signal::SIGINT && shares_group {
+        return Ok(false);
+    }
+
+    send_signal(child, signal)?;
+    Ok(true)
+}
+
+// SIGHUP usually asks a program to reload, the other signals forwarded ask it to exit
+#[cfg(unix)]
+pub(crate) fn is_shutdown_signal(signal: i32) -> bool {
+    signal != nix::sys::signal::Signal::SIGHUP as i32
+}
+
+#[cfg(windows)]
+pub(crate) fn is_shutdown_signal(_signal: i32) -> bool {
+    true
+}
+
+#[cfg(windows)]
+pub(crate) fn forward_signal(_child: &Child, _signal: i32) -> Result<bool> {
+    // Ctrl-C is sent to every process attached to the console, including the child
+    Ok(false)
+}
+
+pub(crate) fn kill_child(child: &mut Child) -> Result<()> {
+    #[cfg(unix)]
+    send_signal(child, nix::sys::signal::Signal::SIGKILL)?;
+    #[cfg(not(unix))]
+    child.start_kill()?;
+
+    Ok(())
+}
+
+pub(crate) async fn stop_child(child: &mut Child, timeout: Duration) -> Result<()> {
+    #[cfg(unix)]
+    {
+        send_signal(child, nix::sys::signal::Signal::SIGTERM)?;
+        if tokio::time::timeout(timeout, child.wait()).await.is_ok() {
+            return Ok(());
+        }
+    }
+
+    kill_child(child)?;
+    child.wait().await?;
+    Ok(())
+}
+
+// The signals that would otherwise terminate `bws` without stopping the child or running any
+// cleanup
+pub(crate) struct TerminationSignals {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    ctrl_c: tokio::signal::windows::CtrlC,
+}
+
+impl TerminationSignals {
+    #[cfg(unix)]
+    pub(crate) fn new() -> Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        Ok(TerminationSignals {
+            hangup: signal(SignalKind::hangup())?,
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn new() -> Result<Self> {
+        Ok(TerminationSignals {
+            ctrl_c: tokio::signal::windows::ctrl_c()?,
+        })
+    }
+
+    #[cfg(unix)]
+    pub(crate) async fn recv(&mut self) -> i32 {
+        use tokio::signal::unix::SignalKind;
+
+        let kind = tokio::select! {
+            _ = self.hangup.recv() => SignalKind::hangup(),
+            _ = self.interrupt.recv() => SignalKind::interrupt(),
+            _ = self.terminate.recv() => SignalKind::terminate(),
+        };
+
+        kind.as_raw_value()
+    }
+
+    #[cfg(windows)]
+    pub(crate) async fn recv(&mut self) -> i32 {
+        self.ctrl_c.recv().await;
+
+        // Ctrl-C is reported as SIGINT (2)
+        2
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use nix::sys::signal::Signal;
+
+    use super::*;
+
+    #[test]
+    fn parse_signal_accepts_short_and_long_names() {
+        assert_eq!(Signal::SIGHUP, parse_signal("SIGHUP").unwrap());
+        assert_eq!(Signal::SIGHUP, parse_signal("HUP").unwrap());
+        assert_eq!(Signal::SIGUSR1, parse_signal("usr1").unwrap());
+    }
+
+    #[test]
+    fn hangup_is_not_a_shutdown_signal() {
+        assert!(!is_shutdown_signal(Signal::SIGHUP as i32));
+        assert!(is_shutdown_signal(Signal::SIGTERM as i32));
+        assert!(is_shutdown_signal(Signal::SIGINT as i32));
+    }
+
+    #[test]
+    fn parse_signal_rejects_unknown_names() {
+        assert!(parse_signal("SIGNOPE").is_err());
+        assert!(parse_signal("").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use bitwarden::secrets_manager::secrets::SecretResponse;
+use color_eyre::eyre::{Result, bail};
+use glob::Pattern;
+
+/// Selects the secrets given to the command by key, with glob patterns.
+#[derive(Debug)]
+pub(crate) struct SecretSelection {
+    /// When not empty, only the secrets matching one of these are selected
+    only: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl SecretSelection {
+    pub(crate) fn new(only: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(SecretSelection {
+            only: patterns(only, "--only")?,
+            exclude: patterns(exclude, "--exclude")?,
+        })
+    }
+
+    /// Keeps the selected secrets, failing if a key or pattern given to `--only` matches none of
+    /// them.
+    pub(crate) fn select(&self, secrets: Vec<SecretResponse>) -> Result<Vec<SecretResponse>> {
+        for pattern in &self.only {
+            if !secrets.iter().any(|s| pattern.matches(&s.key)) {
+                match Pattern::escape(pattern.as_str()) == pattern.as_str() {
+                    true => bail!("Secret '{}' not found", pattern),
+                    false => bail!("No secrets with keys matching '{}' found", pattern),
+                }
+            }
+        }
+
+        Ok(secrets
+            .into_iter()
+            .filter(|s| self.only.is_empty() || self.only.iter().any(|p| p.matches(&s.key)))
+            .filter(|s| !self.exclude.iter().any(|p| p.matches(&s.key)))
+            .collect())
+    }
+}
+
+fn patterns(patterns: &[String], option: &str) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| match Pattern::new(pattern) {
+            Ok(pattern) => Ok(pattern),
+            Err(e) => bail!("Invalid {} pattern '{}': {}", option, pattern, e),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::secret;
+
+    fn secrets(keys: &[&str]) -> Vec<SecretResponse> {
+        keys.iter().map(|key| secret(key, "")).collect()
+    }
+
+    fn selected_keys(selection: &SecretSelection, keys: &[&str]) -> Result<Vec<String>> {
+        Ok(selection
+            .select(secrets(keys))?
+            .into_iter()
+            .map(|s| s.key)
+            .collect())
+    }
+
+    #[test]
+    fn select_only_and_exclude() {
+        let keys = ["DB_HOST", "DB_PASSWORD", "API_KEY", "LOG_LEVEL"];
+
+        let selection =
+            SecretSelection::new(&["DB_*".to_owned(), "API_KEY".to_owned()], &[]).unwrap();
+        assert_eq!(
+            vec!["DB_HOST", "DB_PASSWORD", "API_KEY"],
+            selected_keys(&selection, &keys).unwrap()
+        );
+
+        let selection =
+            SecretSelection::new(&["DB_*".to_owned()], &["*PASSWORD".to_owned()]).unwrap();
+        assert_eq!(vec!["DB_HOST"], selected_keys(&selection, &keys).unwrap());
+
+        let selection = SecretSelection::new(&[], &["DB_*".to_owned()]).unwrap();
+        assert_eq!(
+            vec!["API_KEY", "LOG_LEVEL"],
+            selected_keys(&selection, &keys).unwrap()
+        );
+    }
+
+    #[test]
+    fn select_fails_for_missing_keys() {
+        let keys = ["DB_HOST"];
+
+        let selection = SecretSelection::new(&["API_KEY".to_owned()], &[]).unwrap();
+        assert_eq!(
+            "Secret 'API_KEY' not found",
+            selected_keys(&selection, &keys).unwrap_err().to_string()
+        );
+
+        let selection = SecretSelection::new(&["API_*".to_owned()], &[]).unwrap();
+        assert!(selected_keys(&selection, &keys).is_err());
+
+        // Missing keys are only an error when requested
+        let selection = SecretSelection::new(&[], &["API_KEY".to_owned()]).unwrap();
+        assert!(selected_keys(&selection, &keys).is_ok());
+    }
+}
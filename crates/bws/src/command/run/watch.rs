@@ -0,0 +1,103 @@
+use std::{pin::Pin, time::Duration};
+
+use bitwarden::secrets_manager::{
+    SecretsManagerClient,
+    secrets::{SecretResponse, SecretsSyncRequest, SecretsSyncResponse},
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use uuid::Uuid;
+
+type PendingSync<'a> =
+    Pin<Box<dyn Future<Output = (DateTime<Utc>, Result<SecretsSyncResponse>)> + 'a>>;
+
+pub(crate) struct SecretsWatcher<'a> {
+    client: &'a SecretsManagerClient,
+    organization_id: Uuid,
+    project_ids: Vec<Uuid>,
+    interval: Interval,
+    last_synced_date: DateTime<Utc>,
+    // Kept when the caller stops waiting for changes, so that it's resumed rather than restarted
+    sync: Option<PendingSync<'a>>,
+}
+
+impl<'a> SecretsWatcher<'a> {
+    pub(crate) fn new(
+        client: &'a SecretsManagerClient,
+        organization_id: Uuid,
+        project_ids: Vec<Uuid>,
+        interval: Duration,
+        last_synced_date: DateTime<Utc>,
+    ) -> Self {
+        let mut interval = tokio::time::interval_at(Instant::now() + interval, interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        SecretsWatcher {
+            client,
+            organization_id,
+            project_ids,
+            interval,
+            last_synced_date,
+            sync: None,
+        }
+    }
+
+    pub(crate) async fn changed(&mut self) -> Vec<SecretResponse> {
+        loop {
+            let sync = match &mut self.sync {
+                Some(sync) => sync,
+                None => {
+                    self.interval.tick().await;
+                    self.sync.insert(start_sync(
+                        self.client,
+                        self.organization_id,
+                        self.last_synced_date,
+                    ))
+                }
+            };
+
+            let (synced_at, res) = sync.await;
+            self.sync = None;
+
+            match res {
+                Ok(res) => {
+                    self.last_synced_date = synced_at;
+
+                    if let (true, Some(secrets)) = (res.has_changes, res.secrets) {
+                        return secrets.into_iter().filter(|s| self.is_watched(s)).collect();
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Warning: failed to check for changes to secrets: {}", e);
+                }
+            }
+        }
+    }
+
+    fn is_watched(&self, secret: &SecretResponse) -> bool {
+        self.project_ids.is_empty()
+            || secret
+                .project_id
+                .is_some_and(|id| self.project_ids.contains(&id))
+    }
+}
+
+fn start_sync(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    last_synced_date: DateTime<Utc>,
+) -> PendingSync<'_> {
+    Box::pin(async move {
+        let synced_at = Utc::now();
+        let res = client
+            .secrets()
+            .sync(&SecretsSyncRequest {
+                organization_id,
+                last_synced_date: Some(last_synced_date),
+            })
+            .await;
+
+        (synced_at, res.map_err(Into::into))
+    })
+}
@@ -0,0 +1,142 @@
+use std::{io::Write, path::Path, process::Command};
+
+use bitwarden::secrets_manager::secrets::SecretResponse;
+use color_eyre::eyre::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::util::{scrub_file, secrets_temp_dir};
+
+const EDITOR_VAR_NAME: &str = "EDITOR";
+
+const HEADER: &str = "\
+# Edit the key, value and note of the secret, then save and close the editor.
+# Multi-line values are written as an indented block below `value:`, kept as written.
+# Leave the file unchanged to cancel.
+";
+
+/// The fields of a secret that can be changed in the editor.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct EditableSecret {
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) note: String,
+}
+
+/// Opens the secret in `$EDITOR` and returns the edited fields, or `None` if nothing was changed.
+pub(crate) fn edit_in_editor(secret: &SecretResponse) -> Result<Option<EditableSecret>> {
+    let original = EditableSecret {
+        key: secret.key.clone(),
+        value: secret.value.clone(),
+        note: secret.note.clone(),
+    };
+    let content = to_document(&original)?;
+
+    // Created readable by the current user only, in memory when possible, and overwritten before
+    // it's removed
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("bws-secret-").suffix(".yaml");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o600));
+    }
+    let mut file = builder.tempfile_in(secrets_temp_dir())?;
+    file.write_all(content.as_bytes())?;
+    file.flush()?;
+
+    let edited = open_editor(file.path()).and_then(|()| Ok(std::fs::read_to_string(file.path())?));
+    if let Err(e) = scrub_file(file.path()) {
+        eprintln!(
+            "Warning: failed to overwrite {}: {}",
+            file.path().display(),
+            e
+        );
+    }
+
+    let edited = edited?;
+    if edited == content {
+        return Ok(None);
+    }
+
+    let edited = from_document(&edited)?;
+    if edited == original {
+        return Ok(None);
+    }
+
+    Ok(Some(edited))
+}
+
+fn to_document(secret: &EditableSecret) -> Result<String> {
+    Ok(format!("{}{}", HEADER, serde_yaml::to_string(secret)?))
+}
+
+fn from_document(document: &str) -> Result<EditableSecret> {
+    match serde_yaml::from_str(document) {
+        Ok(secret) => Ok(secret),
+        Err(e) => bail!("Failed to parse the edited secret: {}", e),
+    }
+}
+
+fn open_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var(EDITOR_VAR_NAME).unwrap_or_else(|_| {
+        if cfg!(windows) {
+            "notepad".to_string()
+        } else {
+            "vi".to_string()
+        }
+    });
+
+    // Run through the shell so that editors with arguments, such as `code --wait`, work
+    let status = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(format!("{} \"{}\"", editor, path.display()))
+            .status()
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(path)
+            .status()
+    };
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => bail!("Editor '{}' exited with {}", editor, status),
+        Err(e) => bail!("Failed to start editor '{}': {}", editor, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_roundtrip_keeps_values_as_is() {
+        for value in [
+            "plain",
+            "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n",
+            "trailing lines\n\n\n",
+            "  leading spaces\r\nand crlf",
+            "{\"json\": true}",
+        ] {
+            let secret = EditableSecret {
+                key: "KEY".to_owned(),
+                value: value.to_owned(),
+                note: "a note".to_owned(),
+            };
+
+            let document = to_document(&secret).unwrap();
+            assert!(document.starts_with(HEADER));
+            assert_eq!(secret, from_document(&document).unwrap());
+        }
+    }
+
+    #[test]
+    fn document_rejects_unknown_fields() {
+        assert!(from_document("key: KEY\nvalue: v\nnote: n\nextra: x\n").is_err());
+        assert!(from_document("key: KEY\n").is_err());
+    }
+}
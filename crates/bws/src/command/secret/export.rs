@@ -0,0 +1,250 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient, projects::ProjectsListRequest, secrets::SecretResponse,
+    },
+};
+use clap::ValueEnum;
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::get_secrets;
+use crate::{
+    ExportFormat,
+    command::project::resolve_optional_project_id,
+    crypto::{PassphraseEnvelope, read_passphrase},
+    util::{is_valid_posix_name, write_private_file},
+};
+
+// Path used to write the export to stdout
+const STDOUT_PATH: &str = "-";
+
+#[derive(Debug)]
+pub(crate) struct SecretExportCommandModel {
+    pub(crate) file: PathBuf,
+    pub(crate) project_id: Option<String>,
+    pub(crate) format: ExportFormat,
+    pub(crate) encrypt: bool,
+}
+
+/// The Bitwarden Secrets Manager export format, as used by the web vault.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SecretsManagerExport {
+    pub(crate) projects: Vec<ExportedProject>,
+    pub(crate) secrets: Vec<ExportedSecret>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportedProject {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportedSecret {
+    pub(crate) id: Uuid,
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) note: String,
+    pub(crate) project_ids: Vec<Uuid>,
+}
+
+pub(crate) async fn export(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    export: SecretExportCommandModel,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let project_id =
+        resolve_optional_project_id(&client, organization_id, export.project_id.as_deref()).await?;
+
+    let mut secrets = get_secrets(&client, organization_id, project_id).await?;
+    secrets.sort_by(|a, b| a.key.cmp(&b.key).then(a.id.cmp(&b.id)));
+
+    let content = match export.format {
+        ExportFormat::Env => to_env(&secrets)?,
+        ExportFormat::JSON => format!("{}\n", serde_json::to_string_pretty(&to_map(&secrets)?)?),
+        ExportFormat::YAML => serde_yaml::to_string(&to_map(&secrets)?)?,
+        ExportFormat::Bitwarden => {
+            let project_ids: BTreeSet<Uuid> = secrets.iter().filter_map(|s| s.project_id).collect();
+            let projects = client
+                .projects()
+                .list(&ProjectsListRequest { organization_id })
+                .await?
+                .data
+                .into_iter()
+                .filter(|p| project_ids.contains(&p.id))
+                .map(|p| ExportedProject {
+                    id: p.id,
+                    name: p.name,
+                })
+                .collect();
+
+            let document = SecretsManagerExport {
+                projects,
+                secrets: secrets
+                    .iter()
+                    .map(|s| ExportedSecret {
+                        id: s.id,
+                        key: s.key.clone(),
+                        value: s.value.clone(),
+                        note: s.note.clone(),
+                        project_ids: s.project_id.into_iter().collect(),
+                    })
+                    .collect(),
+            };
+            format!("{}\n", serde_json::to_string_pretty(&document)?)
+        }
+    };
+
+    let content = if export.encrypt {
+        let format = export
+            .format
+            .to_possible_value()
+            .expect("export formats are not skipped");
+        let envelope = PassphraseEnvelope::seal(
+            &read_passphrase(true)?,
+            format.get_name(),
+            content.as_bytes(),
+        )?;
+        format!("{}\n", serde_json::to_string_pretty(&envelope)?)
+    } else {
+        content
+    };
+
+    if export.file == Path::new(STDOUT_PATH) {
+        std::io::stdout().write_all(content.as_bytes())?;
+    } else {
+        write_private_file(&export.file, content.as_bytes())?;
+    }
+
+    match secrets.len() {
+        1 => eprintln!("1 secret exported successfully."),
+        count => eprintln!("{} secrets exported successfully.", count),
+    }
+
+    Ok(())
+}
+
+/// Fails when secrets share a key, which the formats identifying secrets by key can't hold.
+fn check_unique_keys(secrets: &[SecretResponse]) -> Result<()> {
+    if let Some(duplicate) = secrets.iter().map(|s| &s.key).duplicates().next() {
+        bail!(
+            "Multiple secrets with name: '{}'. Use --format bitwarden, --project-id or use unique names for secrets",
+            duplicate
+        );
+    }
+
+    Ok(())
+}
+
+/// Maps keys to values for the formats that can't hold anything else.
+fn to_map(secrets: &[SecretResponse]) -> Result<BTreeMap<&str, &str>> {
+    check_unique_keys(secrets)?;
+
+    Ok(secrets
+        .iter()
+        .map(|s| (s.key.as_str(), s.value.as_str()))
+        .collect())
+}
+
+/// Writes the secrets as double-quoted `KEY="VALUE"` lines that can be sourced by a POSIX shell
+/// and imported back with `bws secret import`.
+fn to_env(secrets: &[SecretResponse]) -> Result<String> {
+    check_unique_keys(secrets)?;
+
+    let mut commented_out = false;
+    let mut lines: Vec<String> = secrets
+        .iter()
+        .map(|s| {
+            let value = escape_env_value(&s.value);
+            if is_valid_posix_name(&s.key) {
+                format!("{}=\"{}\"", s.key, value)
+            } else {
+                commented_out = true;
+                format!("# {}=\"{}\"", s.key, value.replace('\n', "\n# "))
+            }
+        })
+        .collect();
+
+    if commented_out {
+        eprintln!(
+            "Warning: one or more secrets have been commented-out due to a problematic key name"
+        );
+        lines.push(String::from(
+            "\n# one or more secrets have been commented-out due to a problematic key name",
+        ));
+    }
+
+    Ok(lines.into_iter().map(|line| line + "\n").collect())
+}
+
+/// Escapes the characters that keep their special meaning inside double quotes in a POSIX shell.
+fn escape_env_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImportFormat, command::secret::import::parse, test_support::secret};
+
+    #[test]
+    fn env_export_roundtrips_through_import() {
+        let secrets = vec![
+            secret("PLAIN", "value"),
+            secret("SPECIAL", "a \"quoted\" $HOME `cmd` \\ value"),
+            secret("ESCAPES", "C:\\new\\dir \\n \\$"),
+            secret("MULTI", "first\nsecond"),
+        ];
+
+        let imported = parse(&to_env(&secrets).unwrap(), ImportFormat::Env).unwrap();
+        let imported: Vec<(&str, &str)> = imported
+            .iter()
+            .map(|s| (s.key.as_str(), s.value.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("PLAIN", "value"),
+                ("SPECIAL", "a \"quoted\" $HOME `cmd` \\ value"),
+                ("ESCAPES", "C:\\new\\dir \\n \\$"),
+                ("MULTI", "first\nsecond"),
+            ],
+            imported
+        );
+    }
+
+    #[test]
+    fn env_export_comments_out_invalid_names() {
+        let env = to_env(&[secret("db.password", "first\nsecond")]).unwrap();
+
+        assert!(env.starts_with("# db.password=\"first\n# second\"\n"));
+        assert!(parse(&env, ImportFormat::Env).unwrap().is_empty());
+    }
+
+    #[test]
+    fn map_and_env_exports_reject_duplicate_keys() {
+        assert!(to_map(&[secret("KEY", "a"), secret("KEY", "b")]).is_err());
+        assert!(to_env(&[secret("KEY", "a"), secret("KEY", "b")]).is_err());
+    }
+}
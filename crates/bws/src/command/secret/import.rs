@@ -0,0 +1,517 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient,
+        secrets::{SecretCreateRequest, SecretPutRequest, SecretResponse},
+    },
+};
+use clap::ValueEnum;
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::get_secrets;
+use crate::{
+    ExportFormat, ImportFormat,
+    command::project::resolve_project_id,
+    crypto::{PassphraseEnvelope, read_passphrase},
+    render::{OutputSettings, TableSerialize, serialize_response},
+};
+
+#[derive(Debug)]
+pub(crate) struct SecretImportCommandModel {
+    pub(crate) file: PathBuf,
+    pub(crate) project_id: String,
+    pub(crate) format: Option<ImportFormat>,
+    pub(crate) dry_run: bool,
+}
+
+/// A secret read from an import file. A missing note leaves the note of an existing secret as is.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ImportedSecret {
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) note: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ImportAction {
+    Create,
+    Update,
+    Unchanged,
+}
+
+impl fmt::Display for ImportAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportAction::Create => write!(f, "create"),
+            ImportAction::Update => write!(f, "update"),
+            ImportAction::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SecretImportSummary {
+    pub(crate) id: Option<Uuid>,
+    pub(crate) key: String,
+    pub(crate) action: ImportAction,
+}
+
+impl TableSerialize<3> for SecretImportSummary {
+    fn get_headers() -> [&'static str; 3] {
+        ["ID", "Key", "Action"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 3]> {
+        vec![[
+            self.id.map(|id| id.to_string()).unwrap_or_default(),
+            self.key.clone(),
+            self.action.to_string(),
+        ]]
+    }
+}
+
+pub(crate) async fn import(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    import: SecretImportCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let project_id = resolve_project_id(&client, organization_id, &import.project_id).await?;
+
+    let content = std::fs::read_to_string(&import.file)?;
+
+    // Encrypted exports record the format of the data they hold
+    let (content, format) = match serde_json::from_str::<PassphraseEnvelope>(&content) {
+        Ok(envelope) if envelope.encrypted => {
+            let content = String::from_utf8(envelope.open(&read_passphrase(false)?)?)?;
+            (
+                content,
+                Some(ImportFormat::from_export_format(&envelope.format)?),
+            )
+        }
+        _ => (content, import.format),
+    };
+    let format = match format {
+        Some(format) => format,
+        None => ImportFormat::from_path(&import.file)?,
+    };
+    let secrets = parse(&content, format)?;
+
+    if let Some(duplicate) = secrets.iter().map(|s| &s.key).duplicates().next() {
+        bail!(
+            "Multiple secrets with name: '{}' in {}",
+            duplicate,
+            import.file.display()
+        );
+    }
+
+    let existing = get_secrets(&client, organization_id, Some(project_id)).await?;
+    if let Some(duplicate) = existing.iter().map(|s| &s.key).duplicates().next() {
+        bail!(
+            "Multiple secrets with name: '{}' in the project. Use unique names for secrets",
+            duplicate
+        );
+    }
+    let existing: HashMap<&str, &SecretResponse> =
+        existing.iter().map(|s| (s.key.as_str(), s)).collect();
+
+    let plan: Vec<(ImportedSecret, Option<&SecretResponse>, ImportAction)> = secrets
+        .into_iter()
+        .map(|secret| {
+            let current = existing.get(secret.key.as_str()).copied();
+            let action = match current {
+                None => ImportAction::Create,
+                Some(current)
+                    if current.value != secret.value
+                        || secret.note.as_ref().is_some_and(|n| n != &current.note) =>
+                {
+                    ImportAction::Update
+                }
+                Some(_) => ImportAction::Unchanged,
+            };
+            (secret, current, action)
+        })
+        .collect();
+
+    if import.dry_run {
+        let summary: Vec<SecretImportSummary> = plan
+            .into_iter()
+            .map(|(secret, current, action)| SecretImportSummary {
+                id: current.map(|c| c.id),
+                key: secret.key,
+                action,
+            })
+            .collect();
+        serialize_response(summary, output_settings);
+        eprintln!("Dry run, no changes were made.");
+
+        return Ok(());
+    }
+
+    let mut summary = Vec::new();
+    let mut secrets_failed: Vec<(String, String)> = Vec::new();
+    for (secret, current, action) in plan {
+        let result = match (action, current) {
+            (ImportAction::Create, _) => client
+                .secrets()
+                .create(&SecretCreateRequest {
+                    organization_id,
+                    key: secret.key.clone(),
+                    value: secret.value,
+                    note: secret.note.unwrap_or_default(),
+                    project_ids: Some(vec![project_id]),
+                })
+                .await
+                .map(|s| s.id),
+            (ImportAction::Update, Some(current)) => client
+                .secrets()
+                .update(&SecretPutRequest {
+                    id: current.id,
+                    organization_id,
+                    key: secret.key.clone(),
+                    value_changed: secret.value != current.value,
+                    value: secret.value,
+                    note: secret.note.unwrap_or_else(|| current.note.clone()),
+                    project_ids: Some(vec![project_id]),
+                })
+                .await
+                .map(|s| s.id),
+            (_, current) => Ok(current.map(|c| c.id).unwrap_or_default()),
+        };
+
+        match result {
+            Ok(id) => summary.push(SecretImportSummary {
+                id: Some(id),
+                key: secret.key,
+                action,
+            }),
+            Err(e) => secrets_failed.push((secret.key, e.to_string())),
+        }
+    }
+
+    serialize_response(summary, output_settings);
+
+    match secrets_failed.len() {
+        2.. => eprintln!("{} secrets had errors:", secrets_failed.len()),
+        1 => eprintln!("{} secret had an error:", secrets_failed.len()),
+        _ => (),
+    }
+
+    for secret in &secrets_failed {
+        eprintln!("{}: {}", secret.0, secret.1);
+    }
+
+    if !secrets_failed.is_empty() {
+        bail!("Errors when attempting to import secrets.");
+    }
+
+    Ok(())
+}
+
+impl ImportFormat {
+    fn from_export_format(name: &str) -> Result<Self> {
+        match ImportFormat::from_str(name, true) {
+            Ok(format) => Ok(format),
+            // Bitwarden exports are JSON documents
+            Err(_) if ExportFormat::from_str(name, true) == Ok(ExportFormat::Bitwarden) => {
+                Ok(ImportFormat::JSON)
+            }
+            Err(e) => bail!("Unsupported export format: {}", e),
+        }
+    }
+
+    fn from_path(path: &Path) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match file_name.rsplit_once('.') {
+            Some((_, "env")) => Ok(ImportFormat::Env),
+            Some((_, "json")) => Ok(ImportFormat::JSON),
+            Some((_, "yaml" | "yml")) => Ok(ImportFormat::YAML),
+            _ => bail!(
+                "Couldn't detect the format of {}, please provide it with --format",
+                path.display()
+            ),
+        }
+    }
+}
+
+pub(crate) fn parse(content: &str, format: ImportFormat) -> Result<Vec<ImportedSecret>> {
+    match format {
+        ImportFormat::Env => parse_env(content),
+        ImportFormat::JSON => parse_document(serde_json::from_str(content)?),
+        ImportFormat::YAML => parse_document(serde_yaml::from_str(content)?),
+    }
+}
+
+/// Parses a `KEY=VALUE` file, as written by `bws secret export --format env`.
+///
+/// Double-quoted values can span multiple lines and support the `\n`, `\"`, `\\`, `\$` and `` \` ``
+/// escapes, while single-quoted values are taken literally. The env output of other commands, such
+/// as `bws secret list --output env`, doesn't escape values, so values containing `\`, `"`, `$` or
+/// `` ` `` don't round-trip through it.
+fn parse_env(content: &str) -> Result<Vec<ImportedSecret>> {
+    let mut secrets = Vec::new();
+    let mut lines = content.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("Line {}: expected KEY=VALUE", line_number);
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("Line {}: missing key", line_number);
+        }
+
+        let value = value.trim_start();
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            let mut quoted = quoted.to_owned();
+            loop {
+                if let Some(end) = find_closing_quote(&quoted) {
+                    break unescape(&quoted[..end]);
+                }
+                match lines.next() {
+                    Some((_, next)) => {
+                        quoted.push('\n');
+                        quoted.push_str(next);
+                    }
+                    None => bail!("Line {}: unterminated quoted value", line_number),
+                }
+            }
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            match quoted.split_once('\'') {
+                Some((value, _)) => value.to_owned(),
+                None => bail!("Line {}: unterminated quoted value", line_number),
+            }
+        } else {
+            let value = value.split_once(" #").map_or(value, |(value, _)| value);
+            value.trim_end().to_owned()
+        };
+
+        secrets.push(ImportedSecret {
+            key: key.to_owned(),
+            value,
+            note: None,
+        });
+    }
+
+    Ok(secrets)
+}
+
+fn find_closing_quote(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return Some(i),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => value.push('\n'),
+            ('\\', Some(c @ ('"' | '\\' | '$' | '`'))) => value.push(c),
+            (c, _) => {
+                value.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+
+    value
+}
+
+/// Reads the secrets from a JSON or YAML document, which is either a map of keys to values, a list
+/// of objects with `key`, `value` and an optional `note`, as written by `bws secret list`, or a
+/// Bitwarden export holding such a list under `secrets`.
+fn parse_document(document: serde_json::Value) -> Result<Vec<ImportedSecret>> {
+    use serde_json::Value;
+
+    let scalar_to_string = |key: &str, value: Value| -> Result<String> {
+        match value {
+            Value::String(s) => Ok(s),
+            Value::Number(n) => Ok(n.to_string()),
+            Value::Bool(b) => Ok(b.to_string()),
+            _ => bail!("The value of '{}' must be a string, number or boolean", key),
+        }
+    };
+
+    match document {
+        Value::Object(mut map) if map.get("secrets").is_some_and(Value::is_array) => {
+            parse_document(map.remove("secrets").unwrap_or_default())
+        }
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| {
+                Ok(ImportedSecret {
+                    value: scalar_to_string(&key, value)?,
+                    key,
+                    note: None,
+                })
+            })
+            .collect(),
+        Value::Array(list) => list
+            .into_iter()
+            .map(|item| {
+                let Value::Object(mut item) = item else {
+                    bail!("Every secret in the list must be an object");
+                };
+                let Some(Value::String(key)) = item.remove("key") else {
+                    bail!("Every secret in the list must have a `key`");
+                };
+                let Some(value) = item.remove("value") else {
+                    bail!("Secret '{}' is missing a `value`", key);
+                };
+                let note = match item.remove("note") {
+                    Some(Value::Null) | None => None,
+                    Some(note) => Some(scalar_to_string(&key, note)?),
+                };
+
+                Ok(ImportedSecret {
+                    value: scalar_to_string(&key, value)?,
+                    key,
+                    note,
+                })
+            })
+            .collect(),
+        _ => bail!("Expected a map of keys to values, or a list of secrets"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(key: &str, value: &str) -> ImportedSecret {
+        ImportedSecret {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn parse_env_file() {
+        let content = r#"
+# A comment
+PLAIN=value
+export EXPORTED = spaced value # trailing comment
+DOUBLE="with \"escapes\"\nand a newline"
+SINGLE='literal \n'
+MULTI="first
+second"
+EMPTY=
+"#;
+
+        assert_eq!(
+            vec![
+                secret("PLAIN", "value"),
+                secret("EXPORTED", "spaced value"),
+                secret("DOUBLE", "with \"escapes\"\nand a newline"),
+                secret("SINGLE", "literal \\n"),
+                secret("MULTI", "first\nsecond"),
+                secret("EMPTY", ""),
+            ],
+            parse(content, ImportFormat::Env).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_env_file_errors() {
+        assert!(parse("NO_EQUALS", ImportFormat::Env).is_err());
+        assert!(parse("=value", ImportFormat::Env).is_err());
+        assert!(parse("KEY=\"unterminated", ImportFormat::Env).is_err());
+    }
+
+    #[test]
+    fn parse_json_map_and_list() {
+        assert_eq!(
+            vec![secret("A", "1"), secret("B", "true"), secret("C", "text")],
+            parse(r#"{"A": 1, "B": true, "C": "text"}"#, ImportFormat::JSON).unwrap()
+        );
+
+        assert_eq!(
+            vec![ImportedSecret {
+                key: "A".to_owned(),
+                value: "text".to_owned(),
+                note: Some("a note".to_owned()),
+            }],
+            parse(
+                r#"[{"id": "ignored", "key": "A", "value": "text", "note": "a note"}]"#,
+                ImportFormat::JSON
+            )
+            .unwrap()
+        );
+
+        assert!(parse(r#"{"A": {"nested": true}}"#, ImportFormat::JSON).is_err());
+    }
+
+    #[test]
+    fn parse_bitwarden_export() {
+        let content = r#"{
+            "projects": [{"id": "ignored", "name": "prod"}],
+            "secrets": [{"id": "ignored", "key": "A", "value": "text", "note": "", "projectIds": []}]
+        }"#;
+
+        assert_eq!(
+            vec![ImportedSecret {
+                key: "A".to_owned(),
+                value: "text".to_owned(),
+                note: Some(String::new()),
+            }],
+            parse(content, ImportFormat::JSON).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_yaml_map() {
+        assert_eq!(
+            vec![secret("PORT", "8080"), secret("URL", "https://example.com")],
+            parse("PORT: 8080\nURL: https://example.com\n", ImportFormat::YAML).unwrap()
+        );
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(
+            ImportFormat::Env,
+            ImportFormat::from_path(Path::new("dir/.env")).unwrap()
+        );
+        assert_eq!(
+            ImportFormat::YAML,
+            ImportFormat::from_path(Path::new("secrets.yml")).unwrap()
+        );
+        assert!(ImportFormat::from_path(Path::new("secrets.txt")).is_err());
+    }
+}
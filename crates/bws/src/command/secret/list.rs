@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient, projects::ProjectsListRequest, secrets::SecretResponse,
+    },
+};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::{get_secrets, list_identifiers};
+use crate::{
+    Output, SecretField,
+    command::project::resolve_optional_project_id,
+    render::{OutputSettings, TableSerialize, format_date, serialize_response, serialize_rows},
+};
+
+#[derive(Debug)]
+pub(crate) struct SecretListCommandModel {
+    pub(crate) project_id: Option<String>,
+    pub(crate) key_glob: Option<String>,
+    pub(crate) key_regex: Option<String>,
+    pub(crate) created_after: Option<String>,
+    pub(crate) revised_after: Option<String>,
+    pub(crate) fields: Vec<SecretField>,
+    pub(crate) no_values: bool,
+}
+
+/// A secret listed without retrieving its value, along with the project it belongs to.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SecretMetadata {
+    pub(crate) id: Uuid,
+    pub(crate) organization_id: Uuid,
+    pub(crate) project_id: Option<Uuid>,
+    pub(crate) project_name: Option<String>,
+    pub(crate) key: String,
+}
+
+impl TableSerialize<3> for SecretMetadata {
+    fn get_headers() -> [&'static str; 3] {
+        ["ID", "Key", "Project"]
+    }
+
+    fn get_values(&self) -> Vec<[String; 3]> {
+        vec![[
+            self.id.to_string(),
+            self.key.clone(),
+            self.project_name.clone().unwrap_or_default(),
+        ]]
+    }
+}
+
+pub(crate) async fn list(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    list: SecretListCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let project_id =
+        resolve_optional_project_id(&client, organization_id, list.project_id.as_deref()).await?;
+
+    let filter = SecretFilter::new(&list)?;
+    let fields = selected_fields(list.fields, list.no_values)?;
+    if (fields.is_some() || list.no_values) && output_settings.output == Output::Env {
+        bail!("--fields and --no-values can't be used with the env output");
+    }
+
+    if list.no_values {
+        if filter.created_after.is_some() || filter.revised_after.is_some() {
+            bail!("--created-after and --revised-after can't be used with --no-values");
+        }
+
+        let with_projects = includes_projects(fields.as_deref());
+        let secrets: Vec<SecretMetadata> =
+            list_metadata(&client, organization_id, project_id, with_projects)
+                .await?
+                .into_iter()
+                .filter(|s| filter.matches_key(&s.key))
+                .collect();
+
+        match fields {
+            Some(fields) => {
+                serialize_fields(&secrets, &fields, SecretField::metadata, output_settings)
+            }
+            None => serialize_response(secrets, output_settings),
+        }
+
+        return Ok(());
+    }
+
+    let secrets: Vec<SecretResponse> = get_secrets(&client, organization_id, project_id)
+        .await?
+        .into_iter()
+        .filter(|s| filter.matches(s))
+        .collect();
+
+    match fields {
+        Some(fields) => serialize_fields(&secrets, &fields, SecretField::json, output_settings),
+        None => serialize_response(secrets, output_settings),
+    }
+
+    Ok(())
+}
+
+/// Lists the secrets without their values. The secret identifiers don't include a project, so
+/// when `with_projects` is set and no project is given, it's found by listing the secrets of each
+/// project, one request per project. The secrets that do include their project can't be retrieved
+/// without their values. Otherwise the projects are left out, or are the given project.
+async fn list_metadata(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    project_id: Option<Uuid>,
+    with_projects: bool,
+) -> Result<Vec<SecretMetadata>> {
+    let identifiers = list_identifiers(client, organization_id, project_id).await?;
+    if !with_projects {
+        return Ok(identifiers
+            .into_iter()
+            .map(|i| SecretMetadata {
+                id: i.id,
+                organization_id: i.organization_id,
+                project_id,
+                project_name: None,
+                key: i.key,
+            })
+            .collect());
+    }
+
+    let projects = client
+        .projects()
+        .list(&ProjectsListRequest { organization_id })
+        .await?
+        .data;
+    let project_name = |id: Uuid| -> Option<String> {
+        projects.iter().find(|p| p.id == id).map(|p| p.name.clone())
+    };
+
+    let mut secret_projects: HashMap<Uuid, Uuid> = HashMap::new();
+    match project_id {
+        Some(project_id) => {
+            secret_projects.extend(identifiers.iter().map(|i| (i.id, project_id)));
+        }
+        None => {
+            for project in &projects {
+                let secrets = list_identifiers(client, organization_id, Some(project.id)).await?;
+                secret_projects.extend(secrets.into_iter().map(|i| (i.id, project.id)));
+            }
+        }
+    }
+
+    Ok(identifiers
+        .into_iter()
+        .map(|i| {
+            let project_id = secret_projects.get(&i.id).copied();
+            SecretMetadata {
+                id: i.id,
+                organization_id: i.organization_id,
+                project_id,
+                project_name: project_id.and_then(project_name),
+                key: i.key,
+            }
+        })
+        .collect())
+}
+
+/// Outputs the selected fields of every item, using `value` to read a field from an item.
+fn serialize_fields<T>(
+    items: &[T],
+    fields: &[SecretField],
+    value: fn(SecretField, &T) -> Value,
+    output_settings: OutputSettings,
+) {
+    let data: Vec<serde_json::Map<String, Value>> = items
+        .iter()
+        .map(|item| {
+            fields
+                .iter()
+                .map(|f| (f.name().to_owned(), value(*f, item)))
+                .collect()
+        })
+        .collect();
+    let headers: Vec<&str> = fields.iter().map(|f| f.header()).collect();
+    let rows = items
+        .iter()
+        .map(|item| {
+            fields
+                .iter()
+                .map(|f| match value(*f, item) {
+                    Value::String(s) => f.format_cell(s),
+                    _ => String::new(),
+                })
+                .collect()
+        })
+        .collect();
+
+    serialize_rows(&data, &headers, rows, output_settings);
+}
+
+/// Whether the output has a project column, which is the case unless other fields are selected.
+fn includes_projects(fields: Option<&[SecretField]>) -> bool {
+    fields.is_none_or(|fields| fields.contains(&SecretField::ProjectId))
+}
+
+/// Works out the fields to output, or `None` to output the secrets as they are.
+fn selected_fields(fields: Vec<SecretField>, no_values: bool) -> Result<Option<Vec<SecretField>>> {
+    if no_values && fields.iter().any(|f| !f.is_metadata()) {
+        bail!(
+            "--fields can only include id, organization_id, project_id and key when using --no-values"
+        );
+    }
+
+    match fields.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(fields.into_iter().unique().collect())),
+    }
+}
+
+struct SecretFilter {
+    key_glob: Option<glob::Pattern>,
+    key_regex: Option<Regex>,
+    created_after: Option<DateTime<Utc>>,
+    revised_after: Option<DateTime<Utc>>,
+}
+
+impl SecretFilter {
+    fn new(list: &SecretListCommandModel) -> Result<Self> {
+        let key_glob = match list.key_glob.as_deref().map(glob::Pattern::new).transpose() {
+            Ok(key_glob) => key_glob,
+            Err(e) => bail!("Invalid --key-glob pattern: {}", e),
+        };
+        let key_regex = match list.key_regex.as_deref().map(Regex::new).transpose() {
+            Ok(key_regex) => key_regex,
+            Err(e) => bail!("Invalid --key-regex expression: {}", e),
+        };
+
+        Ok(SecretFilter {
+            key_glob,
+            key_regex,
+            created_after: list.created_after.as_deref().map(parse_date).transpose()?,
+            revised_after: list.revised_after.as_deref().map(parse_date).transpose()?,
+        })
+    }
+
+    fn matches_key(&self, key: &str) -> bool {
+        self.key_glob.as_ref().is_none_or(|g| g.matches(key))
+            && self.key_regex.as_ref().is_none_or(|r| r.is_match(key))
+    }
+
+    fn matches(&self, secret: &SecretResponse) -> bool {
+        self.matches_key(&secret.key)
+            && self.created_after.is_none_or(|d| secret.creation_date > d)
+            && self.revised_after.is_none_or(|d| secret.revision_date > d)
+    }
+}
+
+/// Parses an RFC 3339 timestamp, or a date which is taken as midnight UTC.
+fn parse_date(date: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Ok(date.to_utc());
+    }
+
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_time(Default::default()).and_utc()),
+        Err(_) => bail!(
+            "'{}' is not a valid date, expected YYYY-MM-DD or an RFC 3339 timestamp",
+            date
+        ),
+    }
+}
+
+impl SecretField {
+    fn header(self) -> &'static str {
+        match self {
+            SecretField::Id => "ID",
+            SecretField::OrganizationId => "Organization ID",
+            SecretField::ProjectId => "Project ID",
+            SecretField::Key => "Key",
+            SecretField::Value => "Value",
+            SecretField::Note => "Note",
+            SecretField::CreationDate => "Creation Date",
+            SecretField::RevisionDate => "Revision Date",
+        }
+    }
+
+    /// The name of the field in the JSON and YAML outputs.
+    fn name(self) -> &'static str {
+        match self {
+            SecretField::Id => "id",
+            SecretField::OrganizationId => "organizationId",
+            SecretField::ProjectId => "projectId",
+            SecretField::Key => "key",
+            SecretField::Value => "value",
+            SecretField::Note => "note",
+            SecretField::CreationDate => "creationDate",
+            SecretField::RevisionDate => "revisionDate",
+        }
+    }
+
+    /// Whether the field is known without retrieving the value of the secret.
+    fn is_metadata(self) -> bool {
+        matches!(
+            self,
+            SecretField::Id
+                | SecretField::OrganizationId
+                | SecretField::ProjectId
+                | SecretField::Key
+        )
+    }
+
+    fn json(self, secret: &SecretResponse) -> Value {
+        let date = |date: &DateTime<Utc>| date.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+
+        match self {
+            SecretField::Id => secret.id.to_string().into(),
+            SecretField::OrganizationId => secret.organization_id.to_string().into(),
+            SecretField::ProjectId => secret.project_id.map(|id| id.to_string()).into(),
+            SecretField::Key => secret.key.clone().into(),
+            SecretField::Value => secret.value.clone().into(),
+            SecretField::Note => secret.note.clone().into(),
+            SecretField::CreationDate => date(&secret.creation_date).into(),
+            SecretField::RevisionDate => date(&secret.revision_date).into(),
+        }
+    }
+
+    fn metadata(self, secret: &SecretMetadata) -> Value {
+        match self {
+            SecretField::Id => secret.id.to_string().into(),
+            SecretField::OrganizationId => secret.organization_id.to_string().into(),
+            SecretField::ProjectId => secret.project_id.map(|id| id.to_string()).into(),
+            SecretField::Key => secret.key.clone().into(),
+            _ => Value::Null,
+        }
+    }
+
+    /// Formats a field for the table and TSV outputs, which show dates like the other commands.
+    fn format_cell(self, value: String) -> String {
+        match self {
+            SecretField::CreationDate | SecretField::RevisionDate => {
+                match DateTime::parse_from_rfc3339(&value) {
+                    Ok(date) => format_date(&date.to_utc()),
+                    Err(_) => value,
+                }
+            }
+            _ => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model() -> SecretListCommandModel {
+        SecretListCommandModel {
+            project_id: None,
+            key_glob: None,
+            key_regex: None,
+            created_after: None,
+            revised_after: None,
+            fields: Vec::new(),
+            no_values: false,
+        }
+    }
+
+    #[test]
+    fn filter_by_key() {
+        let filter = SecretFilter::new(&SecretListCommandModel {
+            key_glob: Some("DB_*".to_owned()),
+            key_regex: Some("PASS(WORD)?$".to_owned()),
+            ..model()
+        })
+        .unwrap();
+
+        assert!(filter.matches_key("DB_PASSWORD"));
+        assert!(filter.matches_key("DB_PASS"));
+        assert!(!filter.matches_key("DB_USER"));
+        assert!(!filter.matches_key("API_PASSWORD"));
+    }
+
+    #[test]
+    fn filter_rejects_invalid_patterns() {
+        assert!(
+            SecretFilter::new(&SecretListCommandModel {
+                key_regex: Some("(".to_owned()),
+                ..model()
+            })
+            .is_err()
+        );
+        assert!(
+            SecretFilter::new(&SecretListCommandModel {
+                created_after: Some("yesterday".to_owned()),
+                ..model()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(
+            "2024-01-31T00:00:00+00:00",
+            parse_date("2024-01-31").unwrap().to_rfc3339()
+        );
+        assert_eq!(
+            "2024-01-31T10:00:00+00:00",
+            parse_date("2024-01-31T12:00:00+02:00")
+                .unwrap()
+                .to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn selected_fields_without_values() {
+        assert_eq!(None, selected_fields(Vec::new(), true).unwrap());
+        assert_eq!(
+            Some(vec![SecretField::Key, SecretField::Id]),
+            selected_fields(
+                vec![SecretField::Key, SecretField::Id, SecretField::Key],
+                true
+            )
+            .unwrap()
+        );
+        assert!(selected_fields(vec![SecretField::Value], true).is_err());
+        assert!(selected_fields(vec![SecretField::CreationDate], true).is_err());
+        assert!(selected_fields(vec![SecretField::CreationDate], false).is_ok());
+    }
+
+    #[test]
+    fn projects_are_only_listed_for_project_columns() {
+        assert!(includes_projects(None));
+        assert!(includes_projects(Some(&[
+            SecretField::Key,
+            SecretField::ProjectId
+        ])));
+        assert!(!includes_projects(Some(&[
+            SecretField::Id,
+            SecretField::Key
+        ])));
+    }
+}
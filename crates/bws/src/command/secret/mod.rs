@@ -0,0 +1,614 @@
+mod editor;
+mod export;
+mod import;
+mod list;
+mod transfer;
+mod value;
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient,
+        secrets::{
+            SecretCreateRequest, SecretGetRequest, SecretIdentifierResponse,
+            SecretIdentifiersByProjectRequest, SecretIdentifiersRequest, SecretPutRequest,
+            SecretResponse, SecretsDeleteRequest, SecretsGetRequest,
+        },
+    },
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use color_eyre::eyre::{Result, bail};
+use export::SecretExportCommandModel;
+use import::SecretImportCommandModel;
+use list::SecretListCommandModel;
+use transfer::Transfer;
+use uuid::Uuid;
+
+use crate::{
+    SecretCommand,
+    cache::SecretsCache,
+    command::project::{resolve_optional_project_id, resolve_project_id},
+    render::{OutputSettings, serialize_response},
+};
+
+#[derive(Debug)]
+pub(crate) struct SecretCreateCommandModel {
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) note: Option<String>,
+    pub(crate) project_id: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct SecretEditCommandModel {
+    pub(crate) secret: String,
+    pub(crate) key: Option<String>,
+    pub(crate) value: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) project_id: Option<String>,
+    pub(crate) lookup_project_id: Option<String>,
+    pub(crate) editor: bool,
+    pub(crate) if_revision: Option<DateTime<Utc>>,
+}
+
+/// Exit code used when a secret was changed by someone else while it was being edited.
+pub(crate) const CONFLICT_EXIT_CODE: i32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Secret {id} was modified at {actual}, expected revision {expected}. Get the secret again and retry the edit"
+)]
+pub(crate) struct RevisionConflict {
+    id: Uuid,
+    expected: String,
+    actual: String,
+}
+
+pub(crate) async fn process_command(
+    command: SecretCommand,
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    output_settings: OutputSettings,
+    cache: Option<SecretsCache>,
+) -> Result<()> {
+    match command {
+        SecretCommand::List {
+            project_id,
+            key_glob,
+            key_regex,
+            created_after,
+            revised_after,
+            fields,
+            no_values,
+        } => {
+            list::list(
+                client,
+                organization_id,
+                SecretListCommandModel {
+                    project_id,
+                    key_glob,
+                    key_regex,
+                    created_after,
+                    revised_after,
+                    fields,
+                    no_values,
+                },
+                output_settings,
+            )
+            .await
+        }
+        SecretCommand::Get {
+            secret,
+            project_id,
+            cache: _,
+        } => {
+            get(
+                client,
+                organization_id,
+                secret,
+                project_id,
+                cache,
+                output_settings,
+            )
+            .await
+        }
+        SecretCommand::Create {
+            key,
+            value,
+            note,
+            project_id,
+            generate,
+            generator,
+            value_file,
+            value_stdin,
+        } => {
+            let value = match value::generated_value(&client, generate, &generator, "--generate")? {
+                Some(value) => value,
+                None => match value::read_value(value, value_file, value_stdin)? {
+                    Some(value) => value,
+                    None => bail!(
+                        "A value is required. Give it as an argument, use --value-file, --value-stdin or --generate"
+                    ),
+                },
+            };
+
+            create(
+                client,
+                organization_id,
+                SecretCreateCommandModel {
+                    key,
+                    value,
+                    note,
+                    project_id,
+                },
+                output_settings,
+            )
+            .await
+        }
+        SecretCommand::Edit {
+            secret,
+            key,
+            value,
+            value_file,
+            value_stdin,
+            regenerate,
+            generator,
+            note,
+            project_id,
+            lookup_project_id,
+            editor,
+            if_revision,
+        } => {
+            let if_revision = match if_revision.as_deref().map(DateTime::parse_from_rfc3339) {
+                Some(Ok(revision)) => Some(revision.to_utc()),
+                Some(Err(_)) => bail!(
+                    "'{}' is not a valid revision date, expected an RFC 3339 timestamp",
+                    if_revision.unwrap_or_default()
+                ),
+                None => None,
+            };
+            let value =
+                match value::generated_value(&client, regenerate, &generator, "--regenerate")? {
+                    Some(value) => Some(value),
+                    None => value::read_value(value, value_file, value_stdin)?,
+                };
+
+            edit(
+                client,
+                organization_id,
+                SecretEditCommandModel {
+                    secret,
+                    key,
+                    value,
+                    note,
+                    project_id,
+                    lookup_project_id,
+                    editor,
+                    if_revision,
+                },
+                output_settings,
+            )
+            .await
+        }
+        SecretCommand::Delete {
+            secrets,
+            project_id,
+        } => delete(client, organization_id, secrets, project_id).await,
+        SecretCommand::Copy(args) => {
+            transfer::transfer(client, organization_id, Transfer::Copy, args.into()).await
+        }
+        SecretCommand::Move(args) => {
+            transfer::transfer(client, organization_id, Transfer::Move, args.into()).await
+        }
+        SecretCommand::Export {
+            file,
+            project_id,
+            format,
+            encrypt,
+        } => {
+            export::export(
+                client,
+                organization_id,
+                SecretExportCommandModel {
+                    file,
+                    project_id,
+                    format,
+                    encrypt,
+                },
+            )
+            .await
+        }
+        SecretCommand::Import {
+            file,
+            project_id,
+            format,
+            dry_run,
+        } => {
+            import::import(
+                client,
+                organization_id,
+                SecretImportCommandModel {
+                    file,
+                    project_id,
+                    format,
+                    dry_run,
+                },
+                output_settings,
+            )
+            .await
+        }
+    }
+}
+
+/// Lists the identifiers of the secrets in a project, or of all the secrets accessible by the
+/// machine account when no project is provided.
+pub(crate) async fn list_identifiers(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    project_id: Option<Uuid>,
+) -> Result<Vec<SecretIdentifierResponse>> {
+    let res = if let Some(project_id) = project_id {
+        client
+            .secrets()
+            .list_by_project(&SecretIdentifiersByProjectRequest { project_id })
+            .await?
+    } else {
+        client
+            .secrets()
+            .list(&SecretIdentifiersRequest { organization_id })
+            .await?
+    };
+
+    Ok(res.data)
+}
+
+/// Retrieves the secrets in a project, or all the secrets accessible by the machine account when
+/// no project is provided.
+pub(crate) async fn get_secrets(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    project_id: Option<Uuid>,
+) -> Result<Vec<SecretResponse>> {
+    let identifiers = list_identifiers(client, organization_id, project_id).await?;
+    if identifiers.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let secret_ids = identifiers.into_iter().map(|e| e.id).collect();
+    let secrets = client
+        .secrets()
+        .get_by_ids(SecretsGetRequest { ids: secret_ids })
+        .await?
+        .data;
+
+    Ok(secrets)
+}
+
+/// Resolves secret IDs or keys to secret IDs. Keys are looked up in a project, given by ID or
+/// name, or among all the secrets accessible by the machine account when no project is provided.
+pub(crate) async fn resolve_secret_ids(
+    client: &SecretsManagerClient,
+    organization_id: Uuid,
+    secrets: &[String],
+    project: Option<&str>,
+) -> Result<Vec<Uuid>> {
+    let identifiers = match secrets.iter().any(|s| Uuid::parse_str(s).is_err()) {
+        true => {
+            let project_id = resolve_optional_project_id(client, organization_id, project).await?;
+            list_identifiers(client, organization_id, project_id).await?
+        }
+        false => Vec::new(),
+    };
+
+    secrets
+        .iter()
+        .map(|secret| match Uuid::parse_str(secret) {
+            Ok(id) => Ok(id),
+            Err(_) => find_secret_id(&identifiers, secret),
+        })
+        .collect()
+}
+
+fn find_secret_id(identifiers: &[SecretIdentifierResponse], key: &str) -> Result<Uuid> {
+    let matches: Vec<Uuid> = identifiers
+        .iter()
+        .filter(|i| i.key == key)
+        .map(|i| i.id)
+        .collect();
+
+    match matches.as_slice() {
+        [id] => Ok(*id),
+        [] => bail!("Secret '{}' not found", key),
+        _ => bail!(
+            "Multiple secrets with name: '{}'. Use the secret ID, look it up in a single project or use unique names for secrets",
+            key
+        ),
+    }
+}
+
+pub(crate) async fn get(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    secret: String,
+    project_id: Option<String>,
+    cache: Option<SecretsCache>,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let request = serde_json::json!(["secret get", project_id, secret]).to_string();
+
+    let fetch = async {
+        let secret_id =
+            resolve_secret_ids(&client, organization_id, &[secret], project_id.as_deref()).await?
+                [0];
+
+        Ok(client
+            .secrets()
+            .get(&SecretGetRequest { id: secret_id })
+            .await?)
+    };
+
+    let secret = match &cache {
+        Some(cache) => cache.cached(organization_id, &request, fetch).await?,
+        None => fetch.await?,
+    };
+    serialize_response(secret, output_settings);
+
+    Ok(())
+}
+
+pub(crate) async fn create(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    secret: SecretCreateCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let project_id = resolve_project_id(&client, organization_id, &secret.project_id).await?;
+
+    let secret = client
+        .secrets()
+        .create(&SecretCreateRequest {
+            organization_id,
+            key: secret.key,
+            value: secret.value,
+            note: secret.note.unwrap_or_default(),
+            project_ids: Some(vec![project_id]),
+        })
+        .await?;
+    serialize_response(secret, output_settings);
+
+    Ok(())
+}
+
+pub(crate) async fn edit(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    mut secret: SecretEditCommandModel,
+    output_settings: OutputSettings,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let id = resolve_secret_ids(
+        &client,
+        organization_id,
+        std::slice::from_ref(&secret.secret),
+        secret.lookup_project_id.as_deref(),
+    )
+    .await?[0];
+
+    // The edit is based on the secret as it was at the start of the command
+    let old_secret = client.secrets().get(&SecretGetRequest { id }).await?;
+    if let Some(expected) = secret.if_revision {
+        check_revision(id, expected, old_secret.revision_date)?;
+    }
+
+    let project_id =
+        resolve_optional_project_id(&client, organization_id, secret.project_id.as_deref()).await?;
+
+    if secret.editor {
+        match editor::edit_in_editor(&old_secret)? {
+            Some(edited) => {
+                secret.key = Some(edited.key);
+                secret.value = Some(edited.value);
+                secret.note = Some(edited.note);
+            }
+            None if project_id.is_none() => {
+                eprintln!("No changes made to the secret.");
+                return Ok(());
+            }
+            None => (),
+        }
+    }
+
+    let value_changed = secret
+        .value
+        .as_ref()
+        .map_or(false, |v| v != &old_secret.value);
+
+    let request = SecretPutRequest {
+        id,
+        organization_id,
+        key: secret.key.unwrap_or(old_secret.key),
+        value: secret.value.unwrap_or(old_secret.value.clone()),
+        note: secret.note.unwrap_or(old_secret.note),
+        project_ids: project_id.or(old_secret.project_id).map(|id| vec![id]),
+        value_changed,
+    };
+
+    let new_secret = update_if_unchanged(
+        id,
+        old_secret.revision_date,
+        async {
+            Ok(client
+                .secrets()
+                .get(&SecretGetRequest { id })
+                .await?
+                .revision_date)
+        },
+        async { Ok(client.secrets().update(&request).await?) },
+    )
+    .await?;
+    serialize_response(new_secret, output_settings);
+
+    Ok(())
+}
+
+/// Runs `update` only if the secret still has the revision it was read at, as returned by
+/// `current_revision` right before the update.
+///
+/// The API has no conditional update, so this is best effort: changes made between this check and
+/// the update are still overwritten.
+async fn update_if_unchanged<T>(
+    id: Uuid,
+    read_revision: DateTime<Utc>,
+    current_revision: impl Future<Output = Result<DateTime<Utc>>>,
+    update: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    check_revision(id, read_revision, current_revision.await?)?;
+    update.await
+}
+
+fn check_revision(id: Uuid, expected: DateTime<Utc>, actual: DateTime<Utc>) -> Result<()> {
+    if expected != actual {
+        return Err(RevisionConflict {
+            id,
+            expected: expected.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            actual: actual.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn delete(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    secrets: Vec<String>,
+    project_id: Option<String>,
+) -> Result<()> {
+    let secret_ids = resolve_secret_ids(
+        &client,
+        organization_id.into(),
+        &secrets,
+        project_id.as_deref(),
+    )
+    .await?;
+    let count = secret_ids.len();
+
+    let result = client
+        .secrets()
+        .delete(SecretsDeleteRequest { ids: secret_ids })
+        .await?;
+
+    let secrets_failed: Vec<(Uuid, String)> = result
+        .data
+        .into_iter()
+        .filter_map(|r| r.error.map(|e| (r.id, e)))
+        .collect();
+    let deleted_secrets = count - secrets_failed.len();
+
+    report_results(deleted_secrets, &secrets_failed, "deleted", "delete")
+}
+
+/// Prints how many secrets succeeded and the errors of the ones that failed, returning an error
+/// if any failed.
+fn report_results(
+    succeeded: usize,
+    secrets_failed: &[(Uuid, String)],
+    done: &str,
+    action: &str,
+) -> Result<()> {
+    match succeeded {
+        2.. => println!("{} secrets {} successfully.", succeeded, done),
+        1 => println!("{} secret {} successfully.", succeeded, done),
+        _ => (),
+    }
+
+    match secrets_failed.len() {
+        2.. => eprintln!("{} secrets had errors:", secrets_failed.len()),
+        1 => eprintln!("{} secret had an error:", secrets_failed.len()),
+        _ => (),
+    }
+
+    for secret in secrets_failed {
+        eprintln!("{}: {}", secret.0, secret.1);
+    }
+
+    if !secrets_failed.is_empty() {
+        bail!("Errors when attempting to {} secrets.", action);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identifier(key: &str) -> SecretIdentifierResponse {
+        SecretIdentifierResponse {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            key: key.to_owned(),
+        }
+    }
+
+    #[test]
+    fn find_secret_id_by_key() {
+        let identifiers = vec![identifier("A"), identifier("B"), identifier("B")];
+
+        assert_eq!(
+            identifiers[0].id,
+            find_secret_id(&identifiers, "A").unwrap()
+        );
+        assert_eq!(
+            "Secret 'C' not found",
+            find_secret_id(&identifiers, "C").unwrap_err().to_string()
+        );
+        assert!(
+            find_secret_id(&identifiers, "B")
+                .unwrap_err()
+                .to_string()
+                .starts_with("Multiple secrets with name: 'B'")
+        );
+    }
+
+    #[test]
+    fn check_revision_reports_conflicts() {
+        let id = Uuid::new_v4();
+        let read: DateTime<Utc> = "2024-01-31T12:00:00Z".parse().unwrap();
+        let modified: DateTime<Utc> = "2024-01-31T12:00:01Z".parse().unwrap();
+
+        assert!(check_revision(id, read, read).is_ok());
+
+        let error = check_revision(id, read, modified).unwrap_err();
+        assert!(error.downcast_ref::<RevisionConflict>().is_some());
+    }
+
+    #[tokio::test]
+    async fn edits_are_not_applied_over_concurrent_changes() {
+        let id = Uuid::new_v4();
+        let read: DateTime<Utc> = "2024-01-31T12:00:00Z".parse().unwrap();
+        let modified: DateTime<Utc> = "2024-01-31T12:00:01Z".parse().unwrap();
+        let mut updated = false;
+
+        // A plain `bws secret edit --value`, without --editor or --if-revision
+        let error = update_if_unchanged(id, read, async { Ok(modified) }, async {
+            updated = true;
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+        assert!(error.downcast_ref::<RevisionConflict>().is_some());
+        assert!(!updated);
+
+        update_if_unchanged(id, read, async { Ok(read) }, async {
+            updated = true;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert!(updated);
+    }
+}
@@ -0,0 +1,213 @@
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{
+        SecretsManagerClient,
+        secrets::{
+            SecretCreateRequest, SecretIdentifierResponse, SecretPutRequest, SecretsGetRequest,
+        },
+    },
+};
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use uuid::Uuid;
+
+use super::{list_identifiers, report_results, resolve_secret_ids};
+use crate::{
+    SecretTransferArgs,
+    command::project::{resolve_optional_project_id, resolve_project_id},
+};
+
+#[derive(Debug)]
+pub(crate) struct SecretTransferCommandModel {
+    pub(crate) secrets: Vec<String>,
+    pub(crate) key_glob: Option<String>,
+    pub(crate) project_id: Option<String>,
+    pub(crate) to_project_id: String,
+}
+
+impl From<SecretTransferArgs> for SecretTransferCommandModel {
+    fn from(args: SecretTransferArgs) -> Self {
+        SecretTransferCommandModel {
+            secrets: args.secrets,
+            key_glob: args.key_glob,
+            project_id: args.project_id,
+            to_project_id: args.to_project_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Transfer {
+    /// Creates a new secret in the destination project, keeping the original
+    Copy,
+    /// Changes the project of the secret
+    Move,
+}
+
+impl Transfer {
+    fn action(self) -> &'static str {
+        match self {
+            Transfer::Copy => "copy",
+            Transfer::Move => "move",
+        }
+    }
+
+    fn done(self) -> &'static str {
+        match self {
+            Transfer::Copy => "copied",
+            Transfer::Move => "moved",
+        }
+    }
+}
+
+pub(crate) async fn transfer(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    transfer: Transfer,
+    model: SecretTransferCommandModel,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+    let to_project_id = resolve_project_id(&client, organization_id, &model.to_project_id).await?;
+
+    let secret_ids = match &model.key_glob {
+        Some(key_glob) => {
+            let pattern = match glob::Pattern::new(key_glob) {
+                Ok(pattern) => pattern,
+                Err(e) => bail!("Invalid --key-glob pattern: {}", e),
+            };
+            let project_id =
+                resolve_optional_project_id(&client, organization_id, model.project_id.as_deref())
+                    .await?;
+            let identifiers = list_identifiers(&client, organization_id, project_id).await?;
+            let ids = matching_ids(&identifiers, &pattern);
+            if ids.is_empty() {
+                bail!("No secrets with keys matching '{}' found", key_glob);
+            }
+            ids
+        }
+        None => {
+            resolve_secret_ids(
+                &client,
+                organization_id,
+                &model.secrets,
+                model.project_id.as_deref(),
+            )
+            .await?
+        }
+    };
+
+    // Both creating and updating secrets need their values, which identifiers don't have
+    let secrets = client
+        .secrets()
+        .get_by_ids(SecretsGetRequest {
+            ids: secret_ids.clone(),
+        })
+        .await?
+        .data;
+
+    // Secrets that weren't returned can't be transferred, and would otherwise go unreported
+    let returned_ids: Vec<Uuid> = secrets.iter().map(|s| s.id).collect();
+    let mut secrets_failed: Vec<(Uuid, String)> = missing_ids(&secret_ids, &returned_ids)
+        .into_iter()
+        .map(|id| (id, String::from("Secret not found or not accessible")))
+        .collect();
+
+    let mut succeeded = 0;
+    for secret in secrets {
+        let id = secret.id;
+        let project_ids = Some(vec![to_project_id]);
+        let result = match transfer {
+            Transfer::Copy => {
+                client
+                    .secrets()
+                    .create(&SecretCreateRequest {
+                        organization_id,
+                        key: secret.key,
+                        value: secret.value,
+                        note: secret.note,
+                        project_ids,
+                    })
+                    .await
+            }
+            Transfer::Move => {
+                client
+                    .secrets()
+                    .update(&SecretPutRequest {
+                        id,
+                        organization_id,
+                        key: secret.key,
+                        value: secret.value,
+                        note: secret.note,
+                        project_ids,
+                        value_changed: false,
+                    })
+                    .await
+            }
+        };
+
+        match result {
+            Ok(_) => succeeded += 1,
+            Err(e) => secrets_failed.push((id, e.to_string())),
+        }
+    }
+
+    report_results(
+        succeeded,
+        &secrets_failed,
+        transfer.done(),
+        transfer.action(),
+    )
+}
+
+fn matching_ids(identifiers: &[SecretIdentifierResponse], pattern: &glob::Pattern) -> Vec<Uuid> {
+    identifiers
+        .iter()
+        .filter(|i| pattern.matches(&i.key))
+        .map(|i| i.id)
+        .collect()
+}
+
+/// The requested IDs that aren't among the returned ones.
+fn missing_ids(requested: &[Uuid], returned: &[Uuid]) -> Vec<Uuid> {
+    requested
+        .iter()
+        .filter(|id| !returned.contains(id))
+        .unique()
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_ids_by_key_glob() {
+        let identifiers: Vec<SecretIdentifierResponse> = ["DB_HOST", "DB_PASSWORD", "API_KEY"]
+            .into_iter()
+            .map(|key| SecretIdentifierResponse {
+                id: Uuid::new_v4(),
+                organization_id: Uuid::new_v4(),
+                key: key.to_owned(),
+            })
+            .collect();
+
+        assert_eq!(
+            vec![identifiers[0].id, identifiers[1].id],
+            matching_ids(&identifiers, &glob::Pattern::new("DB_*").unwrap())
+        );
+        assert!(matching_ids(&identifiers, &glob::Pattern::new("db_*").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn missing_ids_are_reported_once() {
+        let found = Uuid::new_v4();
+        let missing = Uuid::new_v4();
+
+        assert_eq!(
+            vec![missing],
+            missing_ids(&[found, missing, missing], &[found])
+        );
+        assert!(missing_ids(&[found], &[found]).is_empty());
+    }
+}
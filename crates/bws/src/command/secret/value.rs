@@ -0,0 +1,133 @@
+use std::{io::Read, path::PathBuf};
+
+use bitwarden::{generators::PasswordGeneratorRequest, secrets_manager::SecretsManagerClient};
+use color_eyre::eyre::{Result, bail};
+
+use crate::GeneratorArgs;
+
+const DEFAULT_GENERATED_LENGTH: u8 = 32;
+
+// Value used to type the value in at a prompt, so that it doesn't end up in the shell history
+const PROMPT_VALUE: &str = "-";
+
+/// Reads the value given on the command line, prompting for it when it's `-`, or reads it from a
+/// file or stdin. Values read from files or stdin are kept as is, including any trailing newline.
+pub(crate) fn read_value(
+    value: Option<String>,
+    value_file: Option<PathBuf>,
+    value_stdin: bool,
+) -> Result<Option<String>> {
+    if let Some(value) = value {
+        if value != PROMPT_VALUE {
+            return Ok(Some(value));
+        }
+
+        return match rpassword::prompt_password("Value: ") {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => bail!(
+                "Failed to read the value: {}. Use --value-file or --value-stdin when not running in a terminal",
+                e
+            ),
+        };
+    }
+
+    if let Some(path) = value_file {
+        return match std::fs::read_to_string(&path) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => bail!("Failed to read the value from {}: {}", path.display(), e),
+        };
+    }
+
+    if value_stdin {
+        let mut value = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut value) {
+            bail!("Failed to read the value from stdin: {}", e);
+        }
+        return Ok(Some(value));
+    }
+
+    Ok(None)
+}
+
+/// Generates a random value when `generate` is set, as requested with `flag`.
+pub(crate) fn generated_value(
+    client: &SecretsManagerClient,
+    generate: bool,
+    options: &GeneratorArgs,
+    flag: &str,
+) -> Result<Option<String>> {
+    if !generate {
+        if options.is_set() {
+            bail!(
+                "The options for generating a value can only be used with {}",
+                flag
+            );
+        }
+        return Ok(None);
+    }
+
+    Ok(Some(client.generator().password(options.request())?))
+}
+
+impl GeneratorArgs {
+    fn is_set(&self) -> bool {
+        self.length.is_some()
+            || self.no_lowercase
+            || self.no_uppercase
+            || self.no_numbers
+            || self.no_symbols
+            || self.avoid_ambiguous
+    }
+
+    fn request(&self) -> PasswordGeneratorRequest {
+        PasswordGeneratorRequest {
+            lowercase: !self.no_lowercase,
+            uppercase: !self.no_uppercase,
+            numbers: !self.no_numbers,
+            special: !self.no_symbols,
+            length: self.length.unwrap_or(DEFAULT_GENERATED_LENGTH),
+            avoid_ambiguous: self.avoid_ambiguous,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_value_from_file_as_is() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let content = "-----BEGIN CERTIFICATE-----\r\nMIIB\n-----END CERTIFICATE-----\n\n";
+        std::fs::write(file.path(), content).unwrap();
+
+        assert_eq!(
+            Some(content.to_owned()),
+            read_value(None, Some(file.path().to_owned()), false).unwrap()
+        );
+        assert_eq!(
+            Some("inline".to_owned()),
+            read_value(Some("inline".to_owned()), None, false).unwrap()
+        );
+        assert_eq!(None, read_value(None, None, false).unwrap());
+    }
+
+    #[test]
+    fn generator_request_from_options() {
+        let options = GeneratorArgs {
+            length: None,
+            no_lowercase: false,
+            no_uppercase: false,
+            no_numbers: false,
+            no_symbols: true,
+            avoid_ambiguous: false,
+        };
+        assert!(options.is_set());
+
+        let request = options.request();
+        assert_eq!(DEFAULT_GENERATED_LENGTH, request.length);
+        assert!(request.lowercase && request.uppercase && request.numbers);
+        assert!(!request.special);
+    }
+}
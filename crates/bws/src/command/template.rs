@@ -0,0 +1,393 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use bitwarden::{
+    OrganizationId,
+    secrets_manager::{SecretsManagerClient, secrets::SecretsGetRequest},
+};
+use color_eyre::eyre::{Result, bail};
+use itertools::Itertools;
+use uuid::Uuid;
+
+use crate::{
+    TemplateCommand,
+    command::{project::resolve_optional_project_id, secret::list_identifiers},
+    util::write_private_file,
+};
+
+// Path used to read the template from stdin or write the output to stdout
+const STDIO_PATH: &str = "-";
+
+pub(crate) async fn process_command(
+    command: TemplateCommand,
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+) -> Result<()> {
+    match command {
+        TemplateCommand::Render {
+            template,
+            destination,
+            project_id,
+        } => render(client, organization_id, template, destination, project_id).await,
+    }
+}
+
+pub(crate) async fn render(
+    client: SecretsManagerClient,
+    organization_id: OrganizationId,
+    template_path: PathBuf,
+    destination: PathBuf,
+    project_id: Option<String>,
+) -> Result<()> {
+    let organization_id: Uuid = organization_id.into();
+
+    let template = if template_path == Path::new(STDIO_PATH) {
+        let mut buffer = String::new();
+        std::io::stdin().read_to_string(&mut buffer)?;
+        buffer
+    } else {
+        std::fs::read_to_string(&template_path)?
+    };
+
+    let placeholders = match parse(&template) {
+        Ok(placeholders) => placeholders,
+        Err(e) => bail!("{}:{}", template_path.display(), e),
+    };
+
+    let project_id =
+        resolve_optional_project_id(&client, organization_id, project_id.as_deref()).await?;
+    let identifiers = list_identifiers(&client, organization_id, project_id).await?;
+
+    // Resolve every placeholder before fetching anything, so that all the values can be retrieved
+    // in a single request
+    let mut secret_ids = Vec::with_capacity(placeholders.len());
+    for placeholder in &placeholders {
+        let matches: Vec<Uuid> = identifiers
+            .iter()
+            .filter(|i| match &placeholder.reference {
+                Reference::Key(key) => &i.key == key,
+                Reference::Id(id) => &i.id == id,
+            })
+            .map(|i| i.id)
+            .collect();
+
+        match matches.as_slice() {
+            [id] => secret_ids.push(*id),
+            [] => bail!(
+                "{}:{}:{}: Secret {} not found",
+                template_path.display(),
+                placeholder.line,
+                placeholder.column,
+                placeholder.reference,
+            ),
+            _ => bail!(
+                "{}:{}:{}: Multiple secrets with name: {}. Use bws_id or use unique names for secrets",
+                template_path.display(),
+                placeholder.line,
+                placeholder.column,
+                placeholder.reference,
+            ),
+        }
+    }
+
+    let values: HashMap<Uuid, String> = if secret_ids.is_empty() {
+        HashMap::new()
+    } else {
+        client
+            .secrets()
+            .get_by_ids(SecretsGetRequest {
+                ids: secret_ids.iter().copied().unique().collect(),
+            })
+            .await?
+            .data
+            .into_iter()
+            .map(|s| (s.id, s.value))
+            .collect()
+    };
+
+    let rendered = match substitute(&template, &placeholders, &secret_ids, &values) {
+        Ok(rendered) => rendered,
+        Err(e) => bail!("{}:{}", template_path.display(), e),
+    };
+
+    if destination == Path::new(STDIO_PATH) {
+        std::io::stdout().write_all(rendered.as_bytes())?;
+    } else {
+        write_private_file(&destination, rendered.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Replaces every placeholder with the value of the secret it was resolved to.
+fn substitute(
+    template: &str,
+    placeholders: &[Placeholder],
+    secret_ids: &[Uuid],
+    values: &HashMap<Uuid, String>,
+) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut position = 0;
+    for (placeholder, id) in placeholders.iter().zip(secret_ids) {
+        // The secret can be deleted or become inaccessible after it was listed
+        let Some(value) = values.get(id) else {
+            bail!(
+                "{}:{}: Secret {} could not be retrieved",
+                placeholder.line,
+                placeholder.column,
+                placeholder.reference
+            );
+        };
+
+        rendered.push_str(&template[position..placeholder.start]);
+        rendered.push_str(value);
+        position = placeholder.end;
+    }
+    rendered.push_str(&template[position..]);
+
+    Ok(rendered)
+}
+
+#[derive(Debug, PartialEq)]
+enum Reference {
+    Key(String),
+    Id(Uuid),
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reference::Key(key) => write!(f, "'{}'", key),
+            Reference::Id(id) => write!(f, "with ID '{}'", id),
+        }
+    }
+}
+
+/// A `{{ bws "KEY" }}` or `{{ bws_id "ID" }}` placeholder found in a template.
+#[derive(Debug, PartialEq)]
+struct Placeholder {
+    /// Byte offset of the opening braces
+    start: usize,
+    /// Byte offset right after the closing braces
+    end: usize,
+    line: usize,
+    column: usize,
+    reference: Reference,
+}
+
+/// Finds all the placeholders in a template.
+///
+/// Any `{{ ... }}` block not starting with `bws` or `bws_id` is left untouched, so templates meant
+/// for other tools can be rendered too.
+fn parse(template: &str) -> Result<Vec<Placeholder>> {
+    let mut placeholders = Vec::new();
+    let mut search_from = 0;
+
+    while let Some(offset) = template[search_from..].find("{{") {
+        let start = search_from + offset;
+        search_from = start + 2;
+
+        let mut cursor = Cursor::new(template, search_from);
+        cursor.skip_whitespace();
+        let function = cursor.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if function != "bws" && function != "bws_id" {
+            continue;
+        }
+
+        let (line, column) = line_and_column(template, start);
+        let error = |message: &str| -> Result<Vec<Placeholder>> {
+            bail!(
+                "{}:{}: {} in `{}` placeholder",
+                line,
+                column,
+                message,
+                function
+            )
+        };
+
+        if cursor.skip_whitespace() == 0 {
+            return error("Expected a space");
+        }
+        let Some(argument) = cursor.take_string() else {
+            return error("Expected a quoted string");
+        };
+        cursor.skip_whitespace();
+        if !cursor.take_literal("}}") {
+            return error("Expected `}}`");
+        }
+
+        let reference = if function == "bws_id" {
+            match Uuid::parse_str(&argument) {
+                Ok(id) => Reference::Id(id),
+                Err(_) => return error(&format!("'{}' is not a valid ID", argument)),
+            }
+        } else {
+            Reference::Key(argument)
+        };
+
+        search_from = cursor.position;
+        placeholders.push(Placeholder {
+            start,
+            end: cursor.position,
+            line,
+            column,
+            reference,
+        });
+    }
+
+    Ok(placeholders)
+}
+
+/// Returns the 1-based line and column of a byte offset, counting columns in characters.
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+struct Cursor<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str, position: usize) -> Self {
+        Cursor { text, position }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn skip_whitespace(&mut self) -> usize {
+        self.take_while(|c| c == ' ' || c == '\t').len()
+    }
+
+    fn take_literal(&mut self, literal: &str) -> bool {
+        if self.rest().starts_with(literal) {
+            self.position += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Takes a double-quoted string, supporting `\"` and `\\` escapes.
+    fn take_string(&mut self) -> Option<String> {
+        let mut chars = self.rest().strip_prefix('"')?.char_indices();
+        let mut value = String::new();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    // Account for the opening and closing quotes
+                    self.position += i + 2;
+                    return Some(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => value.push(c),
+                    _ => return None,
+                },
+                '\n' => return None,
+                c => value.push(c),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_finds_placeholders() {
+        let template = "user: {{ bws \"DB_USER\" }}\npassword: {{bws_id \"759130d0-29dd-48bd-831a-e3bdbafeeb6e\"}}\n";
+        let placeholders = parse(template).unwrap();
+
+        assert_eq!(2, placeholders.len());
+        assert_eq!(
+            Reference::Key("DB_USER".to_owned()),
+            placeholders[0].reference
+        );
+        assert_eq!((1, 7), (placeholders[0].line, placeholders[0].column));
+        assert_eq!(
+            "{{ bws \"DB_USER\" }}",
+            &template[placeholders[0].start..placeholders[0].end]
+        );
+        assert_eq!(
+            Reference::Id(Uuid::parse_str("759130d0-29dd-48bd-831a-e3bdbafeeb6e").unwrap()),
+            placeholders[1].reference
+        );
+        assert_eq!((2, 11), (placeholders[1].line, placeholders[1].column));
+    }
+
+    #[test]
+    fn parse_handles_escapes() {
+        let placeholders = parse(r#"{{ bws "a \"quoted\" \\ key" }}"#).unwrap();
+        assert_eq!(
+            Reference::Key(r#"a "quoted" \ key"#.to_owned()),
+            placeholders[0].reference
+        );
+    }
+
+    #[test]
+    fn parse_ignores_other_templates() {
+        let placeholders = parse("{{ .Values.image }} {{bwsx \"KEY\"}} {{ }}").unwrap();
+        assert!(placeholders.is_empty());
+    }
+
+    #[test]
+    fn parse_reports_position_of_malformed_placeholders() {
+        let err = parse("first line\n  ünïcode {{ bws KEY }}").unwrap_err();
+        assert_eq!(
+            "2:11: Expected a quoted string in `bws` placeholder",
+            err.to_string()
+        );
+
+        let err = parse("{{ bws \"KEY\" ").unwrap_err();
+        assert_eq!("1:1: Expected `}}` in `bws` placeholder", err.to_string());
+
+        let err = parse("{{ bws_id \"not-an-id\" }}").unwrap_err();
+        assert_eq!(
+            "1:1: 'not-an-id' is not a valid ID in `bws_id` placeholder",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn substitute_fails_for_missing_values() {
+        let template = "user: {{ bws \"DB_USER\" }}\n  password: {{ bws \"DB_PASSWORD\" }}\n";
+        let placeholders = parse(template).unwrap();
+        let (user, password) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let values = HashMap::from([(user, "admin".to_owned())]);
+        assert_eq!(
+            "2:13: Secret 'DB_PASSWORD' could not be retrieved",
+            substitute(template, &placeholders, &[user, password], &values)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let values = HashMap::from([(user, "admin".to_owned()), (password, "hunter2".to_owned())]);
+        assert_eq!(
+            "user: admin\n  password: hunter2\n",
+            substitute(template, &placeholders, &[user, password], &values).unwrap()
+        );
+    }
+}
@@ -25,6 +25,8 @@ pub(crate) struct Profile {
     pub server_identity: Option<String>,
     pub state_dir: Option<String>,
     pub state_opt_out: Option<String>,
+    pub secrets_cache: Option<String>,
+    pub secrets_cache_ttl: Option<String>,
 }
 
 fn deserialize_trimmed_url<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
@@ -52,6 +54,8 @@ impl ProfileKey {
             ProfileKey::server_identity => p.server_identity = Some(value),
             ProfileKey::state_dir => p.state_dir = Some(value),
             ProfileKey::state_opt_out => p.state_opt_out = Some(value),
+            ProfileKey::secrets_cache => p.secrets_cache = Some(value),
+            ProfileKey::secrets_cache_ttl => p.secrets_cache_ttl = Some(value),
         }
     }
 }
@@ -146,6 +150,8 @@ impl Profile {
             server_identity: None,
             state_dir: None,
             state_opt_out: None,
+            secrets_cache: None,
+            secrets_cache_ttl: None,
         })
     }
 
@@ -0,0 +1,207 @@
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore},
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use color_eyre::eyre::{Result, bail, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::PASSPHRASE_VAR_NAME;
+
+const KDF_ARGON2ID: &str = "argon2id";
+const CIPHER_AES_256_GCM: &str = "aes-256-gcm";
+
+// Same defaults Bitwarden uses for accounts with Argon2id
+const KDF_ITERATIONS: u32 = 3;
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_PARALLELISM: u32 = 4;
+
+// Upper bounds for the parameters read from files, so that opening one can't use unbounded memory
+// or time
+const MAX_KDF_ITERATIONS: u32 = 10;
+const MAX_KDF_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+// The nonce is prepended to the ciphertext
+pub(crate) fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| eyre!("Failed to encrypt data"))?;
+
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+pub(crate) fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < NONCE_LENGTH {
+        bail!("Encrypted data is too short");
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| eyre!("Failed to decrypt data, the key is wrong or the data was modified"))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PassphraseEnvelope {
+    pub(crate) encrypted: bool,
+    pub(crate) format: String,
+    kdf: String,
+    kdf_iterations: u32,
+    kdf_memory: u32,
+    kdf_parallelism: u32,
+    salt: String,
+    cipher: String,
+    data: String,
+}
+
+impl PassphraseEnvelope {
+    pub(crate) fn seal(passphrase: &str, format: &str, plaintext: &[u8]) -> Result<Self> {
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+
+        let key = derive_key(
+            passphrase,
+            &salt,
+            KDF_ITERATIONS,
+            KDF_MEMORY_KIB,
+            KDF_PARALLELISM,
+        )?;
+
+        Ok(PassphraseEnvelope {
+            encrypted: true,
+            format: format.to_owned(),
+            kdf: KDF_ARGON2ID.to_owned(),
+            kdf_iterations: KDF_ITERATIONS,
+            kdf_memory: KDF_MEMORY_KIB,
+            kdf_parallelism: KDF_PARALLELISM,
+            salt: STANDARD.encode(salt),
+            cipher: CIPHER_AES_256_GCM.to_owned(),
+            data: STANDARD.encode(encrypt(&key, plaintext)?),
+        })
+    }
+
+    pub(crate) fn open(&self, passphrase: &str) -> Result<Vec<u8>> {
+        if self.kdf != KDF_ARGON2ID || self.cipher != CIPHER_AES_256_GCM {
+            bail!("Unsupported encryption: {} with {}", self.cipher, self.kdf);
+        }
+        if self.kdf_iterations > MAX_KDF_ITERATIONS
+            || self.kdf_memory > MAX_KDF_MEMORY_KIB
+            || self.kdf_parallelism > MAX_KDF_PARALLELISM
+        {
+            bail!(
+                "Unsupported key derivation parameters, at most {} iterations, {} KiB of memory and a parallelism of {} are allowed",
+                MAX_KDF_ITERATIONS,
+                MAX_KDF_MEMORY_KIB,
+                MAX_KDF_PARALLELISM
+            );
+        }
+
+        let key = derive_key(
+            passphrase,
+            &STANDARD.decode(&self.salt)?,
+            self.kdf_iterations,
+            self.kdf_memory,
+            self.kdf_parallelism,
+        )?;
+
+        decrypt(&key, &STANDARD.decode(&self.data)?)
+    }
+}
+
+pub(crate) fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+    memory_kib: u32,
+    parallelism: u32,
+) -> Result<[u8; 32]> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(32))
+        .map_err(|e| eyre!("Invalid key derivation parameters: {}", e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| eyre!("Failed to derive key from passphrase: {}", e))?;
+
+    Ok(key)
+}
+
+pub(crate) fn read_passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR_NAME) {
+        return Ok(passphrase);
+    }
+
+    let prompt = |message: &str| {
+        rpassword::prompt_password(message).map_err(|e| {
+            eyre!(
+                "Failed to read the passphrase: {}. It can also be provided with {}",
+                e,
+                PASSPHRASE_VAR_NAME
+            )
+        })
+    };
+
+    let passphrase = prompt("Passphrase: ")?;
+    if passphrase.is_empty() {
+        bail!("The passphrase can't be empty");
+    }
+
+    if confirm && prompt("Confirm passphrase: ")? != passphrase {
+        bail!("The passphrases don't match");
+    }
+
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt_roundtrip() {
+        let key = [7u8; 32];
+        let encrypted = encrypt(&key, b"hunter2").unwrap();
+
+        assert_ne!(b"hunter2".as_slice(), &encrypted[NONCE_LENGTH..]);
+        assert_eq!(b"hunter2".as_slice(), decrypt(&key, &encrypted).unwrap());
+        assert!(decrypt(&[8u8; 32], &encrypted).is_err());
+    }
+
+    #[test]
+    fn envelope_roundtrip() {
+        let envelope = PassphraseEnvelope::seal("correct horse", "env", b"KEY=\"value\"").unwrap();
+        let serialized = serde_json::to_string(&envelope).unwrap();
+        let envelope: PassphraseEnvelope = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!("env", envelope.format);
+        assert_eq!(
+            b"KEY=\"value\"".as_slice(),
+            envelope.open("correct horse").unwrap()
+        );
+        assert!(envelope.open("battery staple").is_err());
+    }
+
+    #[test]
+    fn envelope_rejects_excessive_kdf_parameters() {
+        let mut envelope = PassphraseEnvelope::seal("correct horse", "env", b"KEY=value").unwrap();
+        envelope.kdf_memory = 4 * 1024 * 1024;
+
+        let error = envelope.open("correct horse").unwrap_err().to_string();
+        assert!(error.starts_with("Unsupported key derivation parameters"));
+
+        envelope.kdf_memory = KDF_MEMORY_KIB;
+        envelope.kdf_iterations = u32::MAX;
+        assert!(envelope.open("correct horse").is_err());
+    }
+}
@@ -4,17 +4,24 @@ use bitwarden::secrets_manager::{
     AccessToken, AccessTokenLoginRequest, ClientSettings, SecretsManagerClient,
 };
 use bitwarden_cli::install_color_eyre;
+use cache::SecretsCache;
 use clap::{CommandFactory, Parser};
 use color_eyre::eyre::{Result, bail};
+use command::{apply::ApplyCommandModel, run::RunCommandModel};
 use config::Profile;
 use log::error;
 use render::OutputSettings;
 
+mod cache;
 mod cli;
 mod command;
 mod config;
+mod crypto;
+mod naming;
 mod render;
 mod state;
+#[cfg(test)]
+mod test_support;
 mod util;
 
 use crate::cli::*;
@@ -23,7 +30,19 @@ use crate::cli::*;
 async fn main() -> Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    process_commands().await
+    let result = process_commands().await;
+
+    // Conflicts get their own exit code so that scripts can tell them apart and retry
+    if let Err(error) = &result
+        && error
+            .downcast_ref::<command::secret::RevisionConflict>()
+            .is_some()
+    {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(command::secret::CONFLICT_EXIT_CODE);
+    }
+
+    result
 }
 
 async fn process_commands() -> Result<()> {
@@ -84,6 +103,25 @@ async fn process_commands() -> Result<()> {
         })
         .transpose()?;
 
+    // Only `run` and `secret get` can use the secrets cache
+    let cache_args = match &command {
+        Commands::Run { cache, .. }
+        | Commands::Secret {
+            cmd: SecretCommand::Get { cache, .. },
+            ..
+        } => Some(cache),
+        _ => None,
+    };
+    let mut cache = match cache_args {
+        Some(cache_args) => get_secrets_cache(
+            &profile,
+            &access_token,
+            access_token_obj.access_token_id.to_string(),
+            cache_args,
+        )?,
+        None => None,
+    };
+
     let state_file = match get_state_opt_out(&profile) {
         true => None,
         false => match state::get_state_file(
@@ -104,23 +142,50 @@ async fn process_commands() -> Result<()> {
     let client = SecretsManagerClient::new(settings);
 
     // Load session or return if no session exists
-    let _ = client
-        .auth()
-        .login_access_token(&AccessTokenLoginRequest {
-            access_token,
-            state_file,
-        })
-        .await?;
+    if !cache.as_ref().is_some_and(|c| c.is_offline()) {
+        let login = client
+            .auth()
+            .login_access_token(&AccessTokenLoginRequest {
+                access_token,
+                state_file,
+            })
+            .await;
 
-    let organization_id = match client.get_access_token_organization() {
-        Some(id) => id.into(),
-        None => {
-            error!("Access token isn't associated to an organization.");
-            return Ok(());
+        if let Err(e) = login {
+            // The cached secrets can still be used when the server can't be reached, but not when
+            // it refuses the access token
+            let e = color_eyre::Report::from(e);
+            let Some(cache) = cache.as_mut().filter(|_| cache::is_connectivity_error(&e)) else {
+                return Err(e);
+            };
+            eprintln!("Warning: {}\nUsing the cached secrets instead.", e);
+            cache.go_offline();
         }
+    }
+
+    let organization_id = match &cache {
+        Some(cache) if cache.is_offline() => cache.organization_id()?.into(),
+        _ => match client.get_access_token_organization() {
+            Some(id) => id.into(),
+            None => {
+                error!("Access token isn't associated to an organization.");
+                return Ok(());
+            }
+        },
     };
 
-    let output_settings = OutputSettings::new(cli.output, color);
+    // Only `run` and the secret commands turn secret keys into variable names
+    let key_names = match &command {
+        Commands::Run { key_names, .. } | Commands::Secret { key_names, .. } => {
+            naming::NameTransform::new(
+                key_names.uppercase_keys,
+                key_names.sanitize_keys,
+                key_names.key_map.as_deref(),
+            )?
+        }
+        _ => naming::NameTransform::default(),
+    };
+    let output_settings = OutputSettings::new(cli.output, color, key_names);
 
     // And finally we process all the commands which require authentication
     match command {
@@ -128,25 +193,81 @@ async fn process_commands() -> Result<()> {
             command::project::process_command(cmd, client, organization_id, output_settings).await
         }
 
-        Commands::Secret { cmd } => {
-            command::secret::process_command(cmd, client, organization_id, output_settings).await
+        Commands::Secret { cmd, .. } => {
+            command::secret::process_command(cmd, client, organization_id, output_settings, cache)
+                .await
+        }
+
+        Commands::Template { cmd } => {
+            command::template::process_command(cmd, client, organization_id).await
+        }
+
+        Commands::Apply {
+            file,
+            prune,
+            yes,
+            dry_run,
+        } => {
+            command::apply::apply(
+                client,
+                organization_id,
+                ApplyCommandModel {
+                    file,
+                    prune,
+                    yes,
+                    dry_run,
+                },
+            )
+            .await
         }
 
         Commands::Run {
             command,
             shell,
+            no_shell,
+            stdin,
             no_inherit_env,
             project_id,
+            prefix,
+            strip_prefix,
+            only,
+            exclude,
             uuids_as_keynames,
+            mount_dir,
+            watch,
+            watch_interval,
+            on_change,
+            watch_signal,
+            stop_timeout,
+            mask_output,
+            key_names: _,
+            cache: _,
         } => {
             let exit_code = command::run::run(
                 client,
                 organization_id,
-                project_id,
-                uuids_as_keynames,
-                no_inherit_env,
-                shell,
-                command,
+                RunCommandModel {
+                    command,
+                    shell,
+                    no_shell,
+                    stdin,
+                    no_inherit_env,
+                    project_ids: project_id,
+                    prefix,
+                    strip_prefix,
+                    key_names: output_settings.key_names,
+                    only,
+                    exclude,
+                    uuids_as_keynames,
+                    mount_dir,
+                    watch,
+                    watch_interval,
+                    on_change,
+                    watch_signal,
+                    stop_timeout,
+                    mask_output,
+                    cache,
+                },
             )
             .await?;
 
@@ -185,6 +306,49 @@ fn get_config_profile(
     Ok(profile)
 }
 
+fn get_secrets_cache(
+    profile: &Option<Profile>,
+    access_token: &str,
+    access_token_id: String,
+    cache_args: &CacheArgs,
+) -> Result<Option<SecretsCache>> {
+    let enabled = |p: &&Profile| {
+        p.secrets_cache
+            .as_deref()
+            .is_some_and(|c| util::string_to_bool(c).unwrap_or(false))
+    };
+
+    let Some(profile) = profile.as_ref().filter(enabled) else {
+        if cache_args.offline {
+            bail!(
+                "--offline requires the secrets cache, which can be enabled with `bws config secrets-cache true`"
+            );
+        }
+        return Ok(None);
+    };
+
+    let ttl = match &profile.secrets_cache_ttl {
+        Some(ttl) => match ttl.trim().parse() {
+            Ok(ttl) => ttl,
+            Err(_) => bail!("Profile key \"secrets_cache_ttl\" must be a number of seconds"),
+        },
+        None => cache::DEFAULT_TTL_SECONDS,
+    };
+
+    let path = state::get_state_file(
+        profile.state_dir.clone().map(Into::into),
+        format!("{}.secrets", access_token_id),
+    )?;
+
+    Ok(Some(SecretsCache::new(
+        path,
+        access_token,
+        ttl,
+        cache_args.max_stale,
+        cache_args.offline,
+    )?))
+}
+
 fn get_state_opt_out(profile: &Option<Profile>) -> bool {
     if let Some(profile) = profile {
         if let Some(state_opt_out) = &profile.state_opt_out {
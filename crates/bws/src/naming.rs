@@ -0,0 +1,149 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use color_eyre::eyre::{Result, bail};
+
+/// Turns secret keys into environment variable names, for `run` and the env output.
+#[derive(Debug, Default)]
+pub(crate) struct NameTransform {
+    uppercase: bool,
+    sanitize: bool,
+    /// Explicit names for some keys, used as is
+    renames: HashMap<String, String>,
+}
+
+impl NameTransform {
+    pub(crate) fn new(uppercase: bool, sanitize: bool, rename_file: Option<&Path>) -> Result<Self> {
+        let renames = match rename_file {
+            Some(path) => read_renames(path)?,
+            None => HashMap::new(),
+        };
+
+        Ok(NameTransform {
+            uppercase,
+            sanitize,
+            renames,
+        })
+    }
+
+    pub(crate) fn apply(&self, key: &str) -> String {
+        if let Some(name) = self.renames.get(key) {
+            return name.clone();
+        }
+
+        let name = match self.sanitize {
+            true => sanitize(key),
+            false => key.to_owned(),
+        };
+
+        match self.uppercase {
+            true => name.to_uppercase(),
+            false => name,
+        }
+    }
+
+    /// Returns the names that more than one distinct key is transformed into.
+    pub(crate) fn collisions<'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a str>,
+    ) -> HashSet<String> {
+        let mut keys_by_name: HashMap<String, &str> = HashMap::new();
+        let mut collisions = HashSet::new();
+
+        for key in keys {
+            let name = self.apply(key);
+            match keys_by_name.get(&name) {
+                Some(other) if *other != key => {
+                    collisions.insert(name);
+                }
+                Some(_) => (),
+                None => {
+                    keys_by_name.insert(name, key);
+                }
+            }
+        }
+
+        collisions
+    }
+}
+
+/// Reads a JSON or YAML object mapping secret keys to names.
+fn read_renames(path: &Path) -> Result<HashMap<String, String>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => bail!("Failed to read {}: {}", path.display(), e),
+    };
+
+    match serde_yaml::from_str(&content) {
+        Ok(renames) => Ok(renames),
+        Err(e) => bail!(
+            "{} is not a valid key map, expected an object mapping keys to names: {}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+/// Replaces the characters that aren't allowed in POSIX environment variable names with `_`.
+fn sanitize(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::is_valid_posix_name;
+
+    #[test]
+    fn sanitize_invalid_names() {
+        for (key, expected) in [
+            ("db.password", "db_password"),
+            ("api-key", "api_key"),
+            ("1PASSWORD", "_1PASSWORD"),
+            ("", "_"),
+            ("clé", "cl_"),
+        ] {
+            assert_eq!(expected, sanitize(key));
+            assert!(is_valid_posix_name(&sanitize(key)));
+        }
+    }
+
+    #[test]
+    fn renames_take_precedence() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "{\"db.password\": \"DATABASE_PASSWORD\"}").unwrap();
+        let transform = NameTransform::new(true, true, Some(file.path())).unwrap();
+
+        assert_eq!("DATABASE_PASSWORD", transform.apply("db.password"));
+        assert_eq!("API_KEY", transform.apply("api-key"));
+    }
+
+    #[test]
+    fn collisions_after_transformation() {
+        let transform = NameTransform::new(true, true, None).unwrap();
+
+        assert_eq!(
+            HashSet::from(["DB_HOST".to_owned()]),
+            transform.collisions(["db.host", "DB_HOST", "PORT", "PORT"])
+        );
+        assert!(
+            NameTransform::default()
+                .collisions(["db.host", "DB_HOST"])
+                .is_empty()
+        );
+    }
+}
@@ -2,51 +2,73 @@ use bitwarden::secrets_manager::{projects::ProjectResponse, secrets::SecretRespo
 use bitwarden_cli::Color;
 use chrono::{DateTime, Utc};
 use comfy_table::Table;
+use itertools::Itertools;
 use serde::Serialize;
 
-use crate::{cli::Output, util::is_valid_posix_name};
+use crate::{cli::Output, naming::NameTransform, util::is_valid_posix_name};
 
 const ASCII_HEADER_ONLY: &str = "     --            ";
 
 pub(crate) struct OutputSettings {
     pub(crate) output: Output,
     pub(crate) color: Color,
+    /// How secret keys are turned into variable names in the env output
+    pub(crate) key_names: NameTransform,
 }
 
 impl OutputSettings {
-    pub(crate) fn new(output: Output, color: Color) -> Self {
-        OutputSettings { output, color }
+    pub(crate) fn new(output: Output, color: Color, key_names: NameTransform) -> Self {
+        OutputSettings {
+            output,
+            color,
+            key_names,
+        }
     }
 }
 
 pub(crate) fn serialize_response<T: Serialize + TableSerialize<N>, const N: usize>(
     data: T,
     output_settings: OutputSettings,
+) {
+    let rows = data.get_values().into_iter().map(Vec::from).collect();
+    serialize_rows(&data, &T::get_headers(), rows, output_settings);
+}
+
+/// Like [serialize_response], for data whose table columns are only known at runtime. The env
+/// output expects the key and the value in the second and third columns.
+pub(crate) fn serialize_rows<T: Serialize>(
+    data: &T,
+    headers: &[&str],
+    rows: Vec<Vec<String>>,
+    output_settings: OutputSettings,
 ) {
     match output_settings.output {
         Output::JSON => {
             let mut text =
-                serde_json::to_string_pretty(&data).expect("Serialize should be infallible");
+                serde_json::to_string_pretty(data).expect("Serialize should be infallible");
             // Yaml/table/tsv serializations add a newline at the end, so we do the same here for
             // consistency
             text.push('\n');
             pretty_print("json", &text, output_settings.color);
         }
         Output::YAML => {
-            let text = serde_yaml::to_string(&data).expect("Serialize should be infallible");
+            let text = serde_yaml::to_string(data).expect("Serialize should be infallible");
             pretty_print("yaml", &text, output_settings.color);
         }
         Output::Env => {
+            let key_names = &output_settings.key_names;
+            let collisions = key_names.collisions(rows.iter().map(|row| row[1].as_str()));
+
             let mut commented_out = false;
-            let mut text: Vec<String> = data
-                .get_values()
+            let mut text: Vec<String> = rows
                 .into_iter()
                 .map(|row| {
-                    if is_valid_posix_name(&row[1]) {
-                        format!("{}=\"{}\"", row[1], row[2])
+                    let name = key_names.apply(&row[1]);
+                    if is_valid_posix_name(&name) && !collisions.contains(&name) {
+                        format!("{}=\"{}\"", name, row[2])
                     } else {
                         commented_out = true;
-                        format!("# {}=\"{}\"", row[1], row[2].replace('\n', "\n# "))
+                        format!("# {}=\"{}\"", name, row[2].replace('\n', "\n# "))
                     }
                 })
                 .collect();
@@ -56,6 +78,12 @@ pub(crate) fn serialize_response<T: Serialize + TableSerialize<N>, const N: usiz
                     "\n# one or more secrets have been commented-out due to a problematic key name",
                 ));
             }
+            if !collisions.is_empty() {
+                eprintln!(
+                    "Warning: secrets commented-out as different keys have the same name: {}",
+                    collisions.iter().sorted().join(", ")
+                );
+            }
 
             pretty_print(
                 "sh",
@@ -67,19 +95,15 @@ pub(crate) fn serialize_response<T: Serialize + TableSerialize<N>, const N: usiz
             let mut table = Table::new();
             table
                 .load_preset(ASCII_HEADER_ONLY)
-                .set_header(T::get_headers())
-                .add_rows(data.get_values());
+                .set_header(headers)
+                .add_rows(rows);
 
             println!("{table}");
         }
         Output::TSV => {
-            println!("{}", T::get_headers().join("\t"));
+            println!("{}", headers.join("\t"));
 
-            let rows: Vec<String> = data
-                .get_values()
-                .into_iter()
-                .map(|row| row.join("\t"))
-                .collect();
+            let rows: Vec<String> = rows.into_iter().map(|row| row.join("\t")).collect();
             println!("{}", rows.join("\n"));
         }
         Output::None => {}
@@ -120,7 +144,7 @@ impl<T: TableSerialize<N>, const N: usize> TableSerialize<N> for Vec<T> {
     }
 }
 
-fn format_date(date: &DateTime<Utc>) -> String {
+pub(crate) fn format_date(date: &DateTime<Utc>) -> String {
     date.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
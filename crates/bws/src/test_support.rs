@@ -0,0 +1,32 @@
+use bitwarden::secrets_manager::{projects::ProjectResponse, secrets::SecretResponse};
+use uuid::Uuid;
+
+pub(crate) fn secret(key: &str, value: &str) -> SecretResponse {
+    SecretResponse {
+        id: Uuid::new_v4(),
+        organization_id: Uuid::new_v4(),
+        project_id: None,
+        key: key.to_owned(),
+        value: value.to_owned(),
+        note: String::new(),
+        creation_date: Default::default(),
+        revision_date: Default::default(),
+    }
+}
+
+pub(crate) fn project_secret(project_id: Uuid, key: &str, value: &str) -> SecretResponse {
+    SecretResponse {
+        project_id: Some(project_id),
+        ..secret(key, value)
+    }
+}
+
+pub(crate) fn project(name: &str) -> ProjectResponse {
+    ProjectResponse {
+        id: Uuid::new_v4(),
+        organization_id: Uuid::new_v4(),
+        name: name.to_owned(),
+        creation_date: Default::default(),
+        revision_date: Default::default(),
+    }
+}
@@ -1,15 +1,33 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
 use regex::Regex;
 use uuid::Uuid;
 
 const VALID_POSIX_NAME_REGEX: &str = "^[a-zA-Z_][a-zA-Z0-9_]*$";
 const STRING_TO_BOOL_ERROR_MESSAGE: &str = "Could not convert string to bool";
 
+// Memory-backed filesystem available on most Linux systems, used when present so that secrets
+// never touch the disk
+const TMPFS_DIRECTORY: &str = "/dev/shm";
+
 pub(crate) fn is_valid_posix_name(input_text: &str) -> bool {
     Regex::new(VALID_POSIX_NAME_REGEX)
         .expect("VALID_POSIX_NAME_REGEX to be a valid regex")
         .is_match(input_text)
 }
 
+/// Checks whether the input can be used as a single file name, without any path components.
+pub(crate) fn is_valid_file_name(input_text: &str) -> bool {
+    !input_text.is_empty()
+        && input_text != "."
+        && input_text != ".."
+        && !input_text.contains(['/', '\\', '\0'])
+}
+
 pub(crate) fn string_to_bool(value: &str) -> Result<bool, &str> {
     match value.trim().to_lowercase().as_str() {
         "true" | "1" => Ok(true),
@@ -26,6 +44,45 @@ pub(crate) fn uuid_to_posix(uuid: &Uuid) -> String {
     format!("_{}", uuid.to_string().replace('-', "_"))
 }
 
+/// Writes a file that is only readable by the current user, as it contains secrets.
+pub(crate) fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+
+    // The mode only applies to new files, so an existing file is made private before its previous
+    // contents are replaced
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.set_len(0)?;
+    file.write_all(contents)
+}
+
+/// The directory to create temporary files holding secrets in.
+pub(crate) fn secrets_temp_dir() -> PathBuf {
+    let tmpfs = Path::new(TMPFS_DIRECTORY);
+    match tmpfs.is_dir() {
+        true => tmpfs.to_owned(),
+        false => std::env::temp_dir(),
+    }
+}
+
+/// Overwrites a file with zeros, so that its contents don't linger after it's removed.
+pub(crate) fn scrub_file(path: &Path) -> std::io::Result<()> {
+    let len = std::fs::metadata(path)?.len() as usize;
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.write_all(&vec![0; len])?;
+    file.sync_all()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,6 +107,23 @@ mod tests {
         assert!(!is_valid_posix_name("a\nbad\nname"));
     }
 
+    #[test]
+    fn test_is_valid_file_name_true() {
+        assert!(is_valid_file_name("API_KEY"));
+        assert!(is_valid_file_name("db.password"));
+        assert!(is_valid_file_name(".env"));
+    }
+
+    #[test]
+    fn test_is_valid_file_name_false() {
+        assert!(!is_valid_file_name(""));
+        assert!(!is_valid_file_name("."));
+        assert!(!is_valid_file_name(".."));
+        assert!(!is_valid_file_name("nested/name"));
+        assert!(!is_valid_file_name("nested\\name"));
+        assert!(!is_valid_file_name("a\0name"));
+    }
+
     #[test]
     fn test_uuid_to_posix_success() {
         assert_eq!(
@@ -90,4 +164,21 @@ mod tests {
         let result = string_to_bool("hello world");
         assert_eq!(result, Err(STRING_TO_BOOL_ERROR_MESSAGE));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_private_file_restricts_existing_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.env");
+        std::fs::write(&path, "OLD=\"longer previous contents\"\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_file(&path, b"KEY=\"value\"\n").unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+        assert_eq!("KEY=\"value\"\n", std::fs::read_to_string(&path).unwrap());
+    }
 }